@@ -1,3 +1,5 @@
+pub mod social_network;
+
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
@@ -0,0 +1,164 @@
+//! The social network graph of the
+//! [Top Posts](https://link.springer.com/article/10.1007/s10270-021-00927-5)
+//! query, shared between benchmarks.
+use std::{cell::Ref, path::Path};
+
+use depends::{
+    derives::Graph, error::ResolveResult, Dependencies4, GraphCreate, NodeIds, NodeState, Resolve,
+    Visitor,
+};
+use examples::{models::*, *};
+
+use crate::{read_csv_file, read_csv_update, Phase};
+
+#[derive(Graph)]
+#[depends(
+    digraph Dag {
+        comments [label="Comments"];
+        posts [label="Posts"];
+        likes [label="Likes"];
+        comment_to_posts [label="CommentsToPosts"];
+        comments -> comment_to_posts [label="TrackCommentPostIds"];
+        query [label="PostScoresQuery"];
+        comments -> query [label="UpdatePostScoresQuery", class="Dependencies4"];
+        comment_to_posts -> query [label="UpdatePostScoresQuery", class="Dependencies4"];
+        posts -> query [label="UpdatePostScoresQuery", class="Dependencies4"];
+        likes -> query [label="UpdatePostScoresQuery", class="Dependencies4"];
+    }
+)]
+pub struct Foo {}
+
+pub struct GraphOuter<G>(<Foo as GraphCreate>::Graph<G>);
+
+impl<R> GraphOuter<R>
+where
+    for<'a> R: Resolve<Output<'a> = Ref<'a, NodeState<PostScoresQuery>>> + 'a,
+{
+    pub fn init_comments(&self, comments: Vec<Comment>) {
+        for comment in comments {
+            self.0.update_comments(comment).unwrap();
+        }
+    }
+
+    pub fn init_posts(&self, posts: Vec<Post>) {
+        for post in posts {
+            self.0.update_posts(post).unwrap();
+        }
+    }
+
+    pub fn init_likes(&self, likes: Vec<Like>) {
+        for like in likes {
+            self.0.update_likes(like).unwrap();
+        }
+    }
+
+    pub fn apply_updates(&self, updates: Vec<Update>) {
+        for update in updates {
+            match update {
+                Update::Posts(post) => self.0.update_posts(post).unwrap(),
+                Update::Comments(comment) => self.0.update_comments(comment).unwrap(),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R> Resolve for GraphOuter<R>
+where
+    for<'a> R: Resolve<Output<'a> = Ref<'a, NodeState<PostScoresQuery>>> + 'a,
+{
+    type Output<'a>
+        = <R as Resolve>::Output<'a>
+    where
+        Self: 'a;
+
+    fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        self.0.resolve(visitor)
+    }
+}
+
+#[derive(Clone)]
+pub struct InputBatch {
+    comments: Option<Vec<Comment>>,
+    posts: Option<Vec<Post>>,
+    likes: Option<Vec<Like>>,
+    updates: Vec<Vec<Update>>,
+}
+
+impl InputBatch {
+    pub fn new<P: AsRef<Path>>(path: P, model: &str) -> Result<Self, csv::Error> {
+        let dir = path.as_ref().join("models").join(model);
+        let comments = read_csv_file(dir.join("csv-comments-initial.csv"), '|')?;
+        let posts = read_csv_file(dir.join("csv-posts-initial.csv"), '|')?;
+        let likes = read_csv_file(dir.join("csv-likes-initial.csv"), '|')?;
+        let mut updates = Vec::new();
+        for update in 1..=20 {
+            updates.push(read_csv_update(dir.join(format!("change{update:02}.csv")))?);
+        }
+        // Reverse the updates so we can pop them off the end.
+        updates.reverse();
+        Ok(Self {
+            comments: Some(comments),
+            posts: Some(posts),
+            likes: Some(likes),
+            updates,
+        })
+    }
+
+    pub fn initialise_graph<R>(&mut self, graph: &GraphOuter<R>)
+    where
+        for<'a> R: Resolve<Output<'a> = Ref<'a, NodeState<PostScoresQuery>>> + 'a,
+    {
+        graph.init_comments(self.comments.take().unwrap());
+        graph.init_posts(self.posts.take().unwrap());
+        graph.init_likes(self.likes.take().unwrap());
+    }
+
+    pub fn play_update<R>(&mut self, graph: &GraphOuter<R>)
+    where
+        for<'a> R: Resolve<Output<'a> = Ref<'a, NodeState<PostScoresQuery>>> + 'a,
+    {
+        let update = self.updates.pop().unwrap();
+        graph.apply_updates(update);
+    }
+
+    pub fn initialise_to_phase<R>(
+        &mut self,
+        graph: &GraphOuter<R>,
+        visitor: &mut impl Visitor,
+        phase: Phase,
+    ) where
+        for<'a> R: Resolve<Output<'a> = Ref<'a, NodeState<PostScoresQuery>>> + 'a,
+    {
+        self.initialise_graph(graph);
+        if phase == Phase::Updates {
+            graph.resolve_root(visitor).unwrap();
+        }
+    }
+}
+
+/// Create the graph and bring it to the start of `phase` with `visitor`,
+/// returning the graph and the updates left to play.
+///
+/// The graph is created in its own [NodeIds] scope, so its Ids are dense and
+/// start at zero, as visitors indexed by Id require.
+pub fn load_data(
+    mut input_batch: InputBatch,
+    phase: Phase,
+    visitor: &mut impl Visitor,
+) -> (
+    GraphOuter<impl for<'a> Resolve<Output<'a> = Ref<'a, NodeState<PostScoresQuery>>>>,
+    InputBatch,
+) {
+    let graph = NodeIds::new().scope(|| {
+        GraphOuter(Foo::create_dag(
+            Comments::new(),
+            Likes::new(),
+            Posts::new(),
+            CommentsToPosts::new(),
+            PostScoresQuery::new(),
+        ))
+    });
+    input_batch.initialise_to_phase(&graph, visitor, phase);
+    (graph, input_batch)
+}
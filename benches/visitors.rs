@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use benches::{
+    social_network::{load_data, InputBatch},
+    Phase, SocialNetworkConfig,
+};
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BatchSize, BenchmarkGroup, Criterion,
+};
+use depends::{BitSetVisitor, EpochVisitor, HashSetVisitor, Resolve, Visitor};
+use envconfig::Envconfig;
+use examples::models::*;
+use hashbrown::HashSet;
+
+/// Play each update against the graph with `visitor`, which is also used to
+/// bring the graph to the start of the updates.
+fn bench_visitor<V: Visitor + Clone + 'static>(
+    group: &mut BenchmarkGroup<'_, WallTime>,
+    name: &str,
+    visitor: V,
+    input_batch: &InputBatch,
+    expected_result: &HashMap<u32, ExpectedResult>,
+) {
+    group.bench_function(name, |b| {
+        b.iter_batched(
+            || {
+                let mut visitor = visitor.clone();
+                let (graph, input_batch) =
+                    load_data(input_batch.clone(), Phase::Updates, &mut visitor);
+                (visitor, graph, input_batch)
+            },
+            |(mut visitor, graph, mut input_batch)| {
+                for iteration in 1..=20 {
+                    let expected = &expected_result[&iteration];
+                    input_batch.play_update(&graph);
+                    let output = graph.resolve_root(&mut visitor).unwrap();
+                    assert_eq!(output.top_posts(), expected.metric_value);
+                }
+                // important to return the data so it isn't dropped
+                // as part of the benchmark
+                (visitor, graph, input_batch)
+            },
+            BatchSize::SmallInput,
+        );
+    });
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let config = SocialNetworkConfig::init_from_env().unwrap();
+    let exp_results = config.expected_results().unwrap();
+
+    for model in [1_u32, 16, 128, 512] {
+        let expected_result = &exp_results["Q1"][&model];
+        let input_batch = InputBatch::new(&config.csv_dir, model.to_string().as_str()).unwrap();
+
+        let mut group = c.benchmark_group(format!("visitors: {model} - Updates"));
+        bench_visitor(
+            &mut group,
+            "HashSetVisitor",
+            HashSetVisitor::new(),
+            &input_batch,
+            expected_result,
+        );
+        bench_visitor(
+            &mut group,
+            "HashBrownVisitor",
+            HashSet::<usize>::new(),
+            &input_batch,
+            expected_result,
+        );
+        bench_visitor(
+            &mut group,
+            "BitSetVisitor",
+            BitSetVisitor::new(),
+            &input_batch,
+            expected_result,
+        );
+        bench_visitor(
+            &mut group,
+            "BitSetVisitor (foldhash)",
+            BitSetVisitor::with_capacity_and_hasher(0, hashbrown::DefaultHashBuilder::default()),
+            &input_batch,
+            expected_result,
+        );
+        bench_visitor(
+            &mut group,
+            "EpochVisitor",
+            EpochVisitor::new(),
+            &input_batch,
+            expected_result,
+        );
+        group.finish();
+    }
+}
+
+fn configure_criterion() -> Criterion {
+    Criterion::default().configure_from_args().sample_size(100)
+}
+
+criterion_group!(
+    name = benches;
+    config = configure_criterion();
+    targets = criterion_benchmark
+);
+criterion_main!(benches);
@@ -0,0 +1,275 @@
+use std::{cell::RefCell, fmt, future::Future, ops::Deref, ptr::NonNull, rc::Rc};
+
+use crate::execution::{
+    error::ResolveResult, AsyncResolve, Identifiable, Named, Resolve, Staleness, Visitor,
+};
+
+/// The number of nodes in the first chunk of an [Arena] created with
+/// [new](Arena::new).
+const DEFAULT_CAPACITY: usize = 64;
+
+/// # Arena
+///
+/// Contiguous storage for nodes of a single type. Rather than each node being
+/// a separate `Rc` allocation, nodes are allocated in chunks which are never
+/// moved, so resolving a large graph of small nodes walks mostly contiguous
+/// memory.
+///
+/// Nodes are allocated with the `new_in` constructor of the node type, such
+/// as [InputNode::new_in](crate::InputNode::new_in), which returns an
+/// [ArenaRef]. This can be used anywhere an `Rc` of the node can. Nodes live
+/// until both the arena and every [ArenaRef] in to it are dropped.
+///
+/// Creating the nodes of a graph in a [NodeIds](crate::NodeIds) scope gives
+/// them dense Ids, which can be tracked by a
+/// [BitSetVisitor](crate::BitSetVisitor).
+///
+/// ```
+/// # use depends::{Arena, BitSetVisitor, DepRefVec, DependencyVec, DerivedNode, InputNode, NodeIds, Resolve, UpdateDerived};
+/// # use depends::derives::Operation;
+/// # use depends::error::EarlyExit;
+/// # #[derive(Operation)]
+/// # struct Sum;
+/// # impl UpdateDerived<DepRefVec<'_, i64>, Sum> for i64 {
+/// #     fn update(&mut self, deps: DepRefVec<'_, i64>, _: &Sum) -> Result<(), EarlyExit> {
+/// #         *self = deps.iter().map(|dep| *dep.data().value()).sum();
+/// #         Ok(())
+/// #     }
+/// # }
+/// let ids = NodeIds::new();
+/// let inputs = Arena::new();
+/// let derived = Arena::new();
+///
+/// let sum = ids.scope(|| {
+///     let nodes = (0..1000).map(|i| InputNode::new_in(&inputs, i));
+///     DerivedNode::new_in(&derived, DependencyVec::new(nodes), Sum, 0)
+/// });
+///
+/// let mut visitor = BitSetVisitor::new();
+/// assert_eq!(*sum.resolve_root(&mut visitor).unwrap().value(), 499500);
+/// ```
+pub struct Arena<N> {
+    chunks: Rc<Chunks<N>>,
+}
+
+/// The storage of an [Arena], shared with each [ArenaRef].
+struct Chunks<N> {
+    /// Each chunk is allocated with a fixed capacity, which is never
+    /// exceeded, so that its nodes are never moved.
+    chunks: RefCell<Vec<Vec<N>>>,
+}
+
+impl<N> Arena<N> {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Create an arena with space for `capacity` nodes before it must
+    /// allocate again.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            chunks: Rc::new(Chunks {
+                chunks: RefCell::new(vec![Vec::with_capacity(capacity.max(1))]),
+            }),
+        }
+    }
+
+    /// Move `node` in to this arena.
+    pub fn alloc(&self, node: N) -> ArenaRef<N> {
+        let mut chunks = self.chunks.chunks.borrow_mut();
+        let last = chunks.last().expect("an arena always has a chunk");
+        if last.len() == last.capacity() {
+            // Grow geometrically, as `Vec` would.
+            let capacity = last.capacity() * 2;
+            chunks.push(Vec::with_capacity(capacity));
+        }
+        let chunk = chunks.last_mut().expect("an arena always has a chunk");
+        let index = chunk.len();
+        chunk.push(node);
+        // SAFETY: The chunk has spare capacity, so the push didn't reallocate
+        // and no node previously allocated has moved. Chunks are never
+        // shrunk or dropped until the arena is, which the `Rc` prevents
+        // whilst this reference exists.
+        let node = unsafe { NonNull::new_unchecked(chunk.as_mut_ptr().add(index)) };
+        ArenaRef {
+            node,
+            chunks: Rc::clone(&self.chunks),
+        }
+    }
+
+    /// The number of nodes in this arena.
+    pub fn len(&self) -> usize {
+        self.chunks.chunks.borrow().iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<N> Default for Arena<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N> fmt::Debug for Arena<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena").field("len", &self.len()).finish()
+    }
+}
+
+/// A shared reference to a node in an [Arena], which can be cloned and used
+/// as a dependency in the same way as an `Rc`.
+pub struct ArenaRef<N> {
+    node: NonNull<N>,
+    /// Keeps the node alive.
+    chunks: Rc<Chunks<N>>,
+}
+
+impl<N> Clone for ArenaRef<N> {
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node,
+            chunks: Rc::clone(&self.chunks),
+        }
+    }
+}
+
+impl<N> Deref for ArenaRef<N> {
+    type Target = N;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The node is never moved or dropped whilst `chunks` is
+        // alive, and is only ever shared immutably.
+        unsafe { self.node.as_ref() }
+    }
+}
+
+impl<N> Resolve for ArenaRef<N>
+where
+    N: Resolve,
+{
+    type Output<'a>
+        = N::Output<'a>
+    where
+        Self: 'a;
+
+    fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        N::resolve(self, visitor)
+    }
+
+    fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        N::subscribe_stale(self, dependee)
+    }
+
+    fn rollback(&self) {
+        N::rollback(self)
+    }
+}
+
+impl<N> AsyncResolve for ArenaRef<N>
+where
+    N: AsyncResolve,
+{
+    type Output<'a>
+        = N::Output<'a>
+    where
+        Self: 'a;
+
+    fn resolve_async(
+        &self,
+        visitor: &mut impl Visitor,
+    ) -> impl Future<Output = ResolveResult<Self::Output<'_>>> {
+        N::resolve_async(self, visitor)
+    }
+
+    fn rollback(&self) {
+        N::rollback(self)
+    }
+}
+
+impl<N: Named> Named for ArenaRef<N> {
+    fn name() -> &'static str {
+        N::name()
+    }
+
+    fn label(&self) -> &'static str {
+        N::label(self)
+    }
+}
+
+impl<N: Identifiable> Identifiable for ArenaRef<N> {
+    fn id(&self) -> usize {
+        N::id(self)
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use super::*;
+    use crate::{
+        error::EarlyExit,
+        execution::{internal_test_utils::TestData, DiagnosticVisitor, NodeIds},
+        DepRef, Dependency, DerivedNode, InputNode, UpdateDerived,
+    };
+
+    struct Double;
+
+    impl Named for Double {
+        fn name() -> &'static str {
+            "Double"
+        }
+    }
+
+    impl UpdateDerived<DepRef<'_, TestData>, Double> for TestData {
+        fn update(&mut self, deps: DepRef<'_, TestData>, _: &Double) -> Result<(), EarlyExit> {
+            self.inner = deps.data().inner * 2;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_arena() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let inputs = Arena::with_capacity(1);
+        let derived = Arena::with_capacity(1);
+        let nodes = (0..10)
+            .map(|i| {
+                let input = InputNode::new_in(&inputs, TestData::new(i));
+                let double = DerivedNode::new_in(
+                    &derived,
+                    Dependency::new(input.clone()),
+                    Double,
+                    TestData::new(0),
+                );
+                (input, double)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!((inputs.len(), derived.len()), (10, 10));
+        // Nodes don't move as the arena grows.
+        assert_eq!(nodes[0].0.id(), 0);
+        assert_eq!(nodes[9].1.id(), 19);
+
+        let mut visitor = DiagnosticVisitor::new();
+        for (i, (_, double)) in nodes.iter().enumerate() {
+            assert_eq!(double.resolve(&mut visitor).unwrap().inner, i as u32 * 2);
+        }
+        assert_eq!(visitor.recalculated.len(), 10);
+        visitor.clear();
+
+        nodes[3].0.update(100).unwrap();
+        let values = nodes
+            .iter()
+            .map(|(_, double)| double.resolve(&mut visitor).unwrap().inner)
+            .collect::<Vec<_>>();
+        assert_eq!(values[3], 200);
+        assert_eq!(visitor.recalculated, [7].into());
+        visitor.clear();
+
+        // Nodes outlive the arena whilst they're referenced.
+        drop((inputs, derived));
+        assert_eq!(nodes[9].1.resolve(&mut visitor).unwrap().inner, 18);
+    }
+}
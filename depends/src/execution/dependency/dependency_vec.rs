@@ -0,0 +1,347 @@
+use std::{
+    cell::{Ref, RefCell},
+    ops::Deref,
+    rc::Rc,
+};
+
+use crate::execution::{
+    error::ResolveResult, AsyncResolve, Dependency, DependencyEdge, HashValue, Identifiable,
+    IsDirty, Named, NodeState, Resolve, Staleness, Visitor,
+};
+
+/// Short-hand for a reference to a [DependencyVec] of nodes of the same type.
+pub type DepRefVec<'a, T> = DependencyEdges<'a, Ref<'a, NodeState<T>>>;
+
+/// A group of any number of dependencies of the same type, which can be
+/// decided at runtime.
+///
+/// Dependencies can be [pushed](Self::push) and [removed](Self::remove)
+/// between resolves, via
+/// [DerivedNode::dependencies_mut](crate::DerivedNode::dependencies_mut).
+/// A new dependency is always dirty the first time it's resolved, and the ids
+/// of removed dependencies are [reported](DependencyEdges::removed) to the
+/// dependee the next time it's resolved, or the time after if its operation
+/// fails.
+///
+/// ```
+/// # use std::rc::Rc;
+/// # use depends::{DepRefVec, DependencyVec, DerivedNode, HashSetVisitor, Identifiable, InputNode, IsDirty, Resolve, UpdateDerived};
+/// # use depends::derives::Operation;
+/// # use depends::error::EarlyExit;
+/// #[derive(Operation)]
+/// struct Sum;
+///
+/// impl UpdateDerived<DepRefVec<'_, i64>, Sum> for i64 {
+///     fn update(&mut self, deps: DepRefVec<'_, i64>, _: &Sum) -> Result<(), EarlyExit> {
+///         // Only the dependencies which have changed need to be looked at.
+///         for dep in deps.iter().filter(|dep| dep.is_dirty()) {
+///             println!("{} changed", dep.data().value());
+///         }
+///         *self = deps.iter().map(|dep| *dep.data().value()).sum();
+///         Ok(())
+///     }
+/// }
+///
+/// let inputs = (1..=3).map(InputNode::new).collect::<Vec<_>>();
+/// let node = DerivedNode::new(
+///     DependencyVec::new(inputs.iter().map(Rc::clone)),
+///     Sum,
+///     0,
+/// );
+///
+/// let mut visitor = HashSetVisitor::new();
+/// assert_eq!(*node.resolve_root(&mut visitor).unwrap().value(), 6);
+///
+/// inputs[1].update(10).unwrap();
+/// assert_eq!(*node.resolve_root(&mut visitor).unwrap().value(), 14);
+///
+/// // Dependencies can be changed without rebuilding the graph.
+/// let mut dependencies = node.dependencies_mut().unwrap();
+/// dependencies.remove(inputs[0].id());
+/// dependencies.push(InputNode::new(100));
+/// drop(dependencies);
+/// assert_eq!(*node.resolve_root(&mut visitor).unwrap().value(), 113);
+/// ```
+#[derive(Debug)]
+pub struct DependencyVec<T> {
+    dependencies: Vec<Dependency<T>>,
+    /// The ids of dependencies removed since this was last resolved.
+    removed: RefCell<Vec<usize>>,
+    /// The ids reported by the last resolve, in case it's
+    /// [rolled back](Resolve::rollback).
+    reported: RefCell<Vec<usize>>,
+}
+
+impl<T> DependencyVec<T> {
+    pub fn new(dependencies: impl IntoIterator<Item = T>) -> Self {
+        Self {
+            dependencies: dependencies.into_iter().map(Dependency::new).collect(),
+            removed: RefCell::new(Vec::new()),
+            reported: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Add a dependency, which will be dirty when next resolved.
+    pub fn push(&mut self, dependency: T) {
+        self.dependencies.push(Dependency::new(dependency));
+    }
+
+    /// Remove the dependency with the given [id](Identifiable::id), if
+    /// present.
+    pub fn remove(&mut self, id: usize) -> Option<T>
+    where
+        T: Identifiable,
+    {
+        let index = self
+            .dependencies
+            .iter()
+            .position(|dep| dep.dependency().id() == id)?;
+        self.removed.get_mut().push(id);
+        Some(self.dependencies.remove(index).into_inner())
+    }
+
+    pub fn len(&self) -> usize {
+        self.dependencies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dependencies.is_empty()
+    }
+
+    /// Take the ids removed since the last resolve, keeping a copy until the
+    /// next in case this resolve is rolled back.
+    fn take_removed(&self) -> Vec<usize> {
+        let removed = self.removed.take();
+        self.reported.replace(removed.clone());
+        removed
+    }
+
+    /// Report the ids taken by the last resolve again.
+    fn restore_removed(&self) {
+        let reported = self.reported.take();
+        self.removed.borrow_mut().splice(0..0, reported);
+    }
+}
+
+impl<T> Named for DependencyVec<T> {
+    fn name() -> &'static str {
+        "DependencyVec"
+    }
+}
+
+impl<T> Resolve for DependencyVec<T>
+where
+    T: Resolve,
+    for<'a> <T as Resolve>::Output<'a>: HashValue,
+{
+    type Output<'a>
+        = DependencyEdges<'a, T::Output<'a>>
+    where
+        Self: 'a;
+
+    fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        visitor.touch_dependency_group(Self::name());
+        // Nothing is reported if a dependency fails to resolve.
+        self.reported.take();
+        let edges = self
+            .dependencies
+            .iter()
+            .map(|dep| dep.resolve(visitor))
+            .collect::<ResolveResult<_>>()?;
+        Ok(DependencyEdges {
+            edges,
+            removed: self.take_removed(),
+        })
+    }
+
+    fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        self.dependencies
+            .iter()
+            .all(|dep| dep.subscribe_stale(dependee))
+    }
+
+    fn rollback(&self) {
+        self.restore_removed();
+    }
+}
+
+impl<T> AsyncResolve for DependencyVec<T>
+where
+    T: AsyncResolve,
+    for<'a> <T as AsyncResolve>::Output<'a>: HashValue,
+{
+    type Output<'a>
+        = DependencyEdges<'a, <T as AsyncResolve>::Output<'a>>
+    where
+        Self: 'a;
+
+    async fn resolve_async(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        visitor.touch_dependency_group(Self::name());
+        self.reported.take();
+        let mut edges = Vec::with_capacity(self.dependencies.len());
+        for dep in self.dependencies.iter() {
+            edges.push(dep.resolve_async(visitor).await?);
+        }
+        Ok(DependencyEdges {
+            edges,
+            removed: self.take_removed(),
+        })
+    }
+
+    fn rollback(&self) {
+        self.restore_removed();
+    }
+}
+
+/// The resolved edges of a [DependencyVec], in the order the dependencies
+/// were provided. Use [is_dirty](IsDirty::is_dirty) on each edge to find which
+/// have changed.
+#[derive(Debug)]
+pub struct DependencyEdges<'a, T> {
+    edges: Vec<DependencyEdge<'a, T>>,
+    removed: Vec<usize>,
+}
+
+impl<T> DependencyEdges<'_, T> {
+    /// The ids of any dependencies removed since the last resolve.
+    pub fn removed(&self) -> &[usize] {
+        &self.removed
+    }
+}
+
+impl<'a, T> Deref for DependencyEdges<'a, T> {
+    type Target = [DependencyEdge<'a, T>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.edges
+    }
+}
+
+impl<T> IsDirty for DependencyEdges<'_, T> {
+    fn is_dirty(&self) -> bool {
+        !self.removed.is_empty() || self.edges.iter().any(IsDirty::is_dirty)
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::execution::{
+        error::EarlyExit, internal_test_utils::TestData, DerivedNode, HashSetVisitor, Identifiable,
+        InputNode, NodeIds, UpdateDerived,
+    };
+
+    #[test]
+    fn test_dependency_vec() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let nodes = (0..3)
+            .map(|i| InputNode::new(TestData::new(i)))
+            .collect::<Vec<_>>();
+        let dependencies = DependencyVec::new(nodes.iter().map(Rc::clone));
+        assert_eq!(dependencies.len(), 3);
+        let mut visitor = HashSetVisitor::new();
+        let dirty = |visitor: &mut HashSetVisitor| {
+            let edges = dependencies.resolve_root(visitor).unwrap();
+            (
+                edges.is_dirty(),
+                edges.iter().map(IsDirty::is_dirty).collect::<Vec<_>>(),
+            )
+        };
+
+        assert_eq!(dirty(&mut visitor), (true, vec![true, true, true]));
+        assert_eq!(dirty(&mut visitor), (false, vec![false, false, false]));
+        nodes[1].update(5).unwrap();
+        assert_eq!(dirty(&mut visitor), (true, vec![false, true, false]));
+        assert!(DependencyVec::<Rc<InputNode<TestData>>>::new([]).is_empty());
+    }
+
+    #[test]
+    fn test_dependency_vec_membership() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let nodes = (0..3)
+            .map(|i| InputNode::new(TestData::new(i)))
+            .collect::<Vec<_>>();
+        let mut dependencies = DependencyVec::new(nodes.iter().take(2).map(Rc::clone));
+        let mut visitor = HashSetVisitor::new();
+        drop(dependencies.resolve_root(&mut visitor).unwrap());
+
+        // Added dependencies are dirty.
+        dependencies.push(Rc::clone(&nodes[2]));
+        {
+            let edges = dependencies.resolve_root(&mut visitor).unwrap();
+            assert_eq!(
+                edges.iter().map(IsDirty::is_dirty).collect::<Vec<_>>(),
+                vec![false, false, true]
+            );
+            assert!(edges.removed().is_empty());
+        }
+
+        // Removed dependencies are reported once.
+        assert!(dependencies.remove(nodes[0].id()).is_some());
+        assert!(dependencies.remove(nodes[0].id()).is_none());
+        assert_eq!(dependencies.len(), 2);
+        {
+            let edges = dependencies.resolve_root(&mut visitor).unwrap();
+            assert!(edges.is_dirty());
+            assert_eq!(edges.removed(), &[0]);
+            assert!(!edges.iter().any(IsDirty::is_dirty));
+        }
+        let edges = dependencies.resolve_root(&mut visitor).unwrap();
+        assert!(!edges.is_dirty());
+    }
+
+    struct CountRemoved {
+        fail: Rc<Cell<bool>>,
+    }
+
+    impl Named for CountRemoved {
+        fn name() -> &'static str {
+            "CountRemoved"
+        }
+    }
+
+    impl UpdateDerived<DepRefVec<'_, TestData>, CountRemoved> for TestData {
+        fn update(
+            &mut self,
+            deps: DepRefVec<'_, TestData>,
+            operation: &CountRemoved,
+        ) -> Result<(), EarlyExit> {
+            if operation.fail.get() {
+                return Err(EarlyExit::new("failed"));
+            }
+            self.inner += deps.removed().len() as u32;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_dependency_vec_rollback() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let nodes = (0..2)
+            .map(|i| InputNode::new(TestData::new(i)))
+            .collect::<Vec<_>>();
+        let fail = Rc::new(Cell::new(false));
+        let node = DerivedNode::new(
+            DependencyVec::new(nodes.iter().map(Rc::clone)),
+            CountRemoved {
+                fail: Rc::clone(&fail),
+            },
+            TestData::new(0),
+        );
+        let mut visitor = HashSetVisitor::new();
+        assert_eq!(node.resolve_root(&mut visitor).unwrap().value().inner, 0);
+
+        // Removed ids are reported again if the operation fails.
+        node.dependencies_mut().unwrap().remove(nodes[0].id());
+        fail.set(true);
+        assert!(node.resolve_root(&mut visitor).is_err());
+        fail.set(false);
+        assert_eq!(node.resolve_root(&mut visitor).unwrap().value().inner, 1);
+        assert_eq!(node.resolve_root(&mut visitor).unwrap().value().inner, 1);
+    }
+}
@@ -1,45 +1,51 @@
+use std::rc::Rc;
+
 use crate::{
-    error::ResolveResult, Dependency, DependencyEdge, HashValue, IsDirty, Named, NodeRef, Resolve,
-    Visitor,
+    error::ResolveResult,
+    execution::visitor::{resolve_branches, Branch},
+    AsyncResolve, Dependency, DependencyEdge, HashValue, IsDirty, Named, NodeRef, Resolve,
+    Staleness, SyncDependency, SyncNodeRef, Visitor,
 };
 
-macro_rules! generate_dependencies {
-    ($count:expr, $($param:expr),*) => {
+/// Generate a group of `$count` dependencies, each wrapped in `$dep`.
+macro_rules! generate_dependency_group {
+    ($group:ident, $dep:ident, $count:expr, $($param:expr),*) => {
         paste::paste! {
-            pub struct [<Dependencies $count>]<$([<T $param >]),*> (
-                $(Dependency<[<T $param >]>,)*
+            pub struct [<$group $count>]<$([<T $param >]),*> (
+                $($dep<[<T $param >]>,)*
             );
 
-            impl<$([<T $param >]),*> Named for [<Dependencies $count>]<$([<T $param >]),*> {
+            impl<$([<T $param >]),*> Named for [<$group $count>]<$([<T $param >]),*> {
                 fn name() -> &'static str {
-                     stringify!([<Dependencies $count>])
+                     stringify!([<$group $count>])
                 }
             }
 
-            impl<$([<T $param >]),*> [<Dependencies $count>]<$([<T $param >]),*>
-            where
-                $([<T $param >]: Resolve,)*
-                $(for<'a> <[<T $param >] as Resolve>::Output<'a>: HashValue,)*
-            {
+            impl<$([<T $param >]),*> [<$group $count>]<$([<T $param >]),*> {
                 #[allow(clippy::too_many_arguments)]
                 pub fn new($([<t $param >]: [<T $param >]),*) -> Self {
                     Self (
-                        $(Dependency::new([<t $param >])),*
+                        $($dep::new([<t $param >])),*
                     )
                 }
             }
+        }
+    };
+}
 
+macro_rules! generate_dependencies {
+    ($count:expr, $($param:expr),*) => {
+        generate_dependency_group!(Dependencies, Dependency, $count, $($param),*);
+        generate_dependency_group!(SyncDependencies, SyncDependency, $count, $($param),*);
+
+        paste::paste! {
             pub struct [<DependencyReference $count>]<'a, $([<T $param >]),*> (
                 $(pub DependencyEdge<'a, [<T $param >]>,)*
             );
 
             pub type [<DepRef $count>]<'a, $([<T $param >]),*> = [<DependencyReference $count>]<'a, $(NodeRef<'a, [<T $param >]>),*>;
 
-            impl<$([<T $param >]),*> IsDirty for [<DependencyReference $count>]<'_, $([<T $param >]),*> {
-                fn is_dirty(&self) -> bool {
-                    $(self.[< $param >].is_dirty() )||*
-                }
-            }
+            pub type [<SyncDepRef $count>]<'a, $([<T $param >]),*> = [<DependencyReference $count>]<'a, $(SyncNodeRef<'a, [<T $param >]>),*>;
 
             impl<$([<T $param >]),*> Resolve for [<Dependencies $count>]<$([<T $param >]),*>
             where
@@ -56,6 +62,66 @@ macro_rules! generate_dependencies {
                         $(self.[< $param >].resolve(visitor)?),*
                     ))
                 }
+
+                fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+                    $(self.[< $param >].subscribe_stale(dependee))&&*
+                }
+
+                fn rollback(&self) {
+                    $(self.[< $param >].rollback();)*
+                }
+            }
+
+            impl<$([<T $param >]),*> AsyncResolve for [<Dependencies $count>]<$([<T $param >]),*>
+            where
+                $([<T $param >]: AsyncResolve,)*
+                $(for<'a> <[<T $param >] as AsyncResolve>::Output<'a>: HashValue,)*
+            {
+                type Output<'a> = [<DependencyReference $count>]<'a, $(<[<T $param >] as AsyncResolve>::Output<'a>),*>
+                where
+                    Self: 'a;
+
+                async fn resolve_async(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+                    visitor.touch_dependency_group(Self::name());
+                    Ok([<DependencyReference $count>] (
+                        $(self.[< $param >].resolve_async(visitor).await?),*
+                    ))
+                }
+
+                fn rollback(&self) {
+                    $(AsyncResolve::rollback(&self.[< $param >]);)*
+                }
+            }
+
+            impl<$([<T $param >]),*> Resolve for [<SyncDependencies $count>]<$([<T $param >]),*>
+            where
+                $([<T $param >]: Resolve + Sync,)*
+                $(for<'a> <[<T $param >] as Resolve>::Output<'a>: HashValue,)*
+            {
+                type Output<'a> = [<DependencyReference $count>]<'a, $([<T $param >]::Output<'a>),*>
+                where
+                    Self: 'a;
+
+                fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+                    visitor.touch_dependency_group(Self::name());
+                    // Resolve each dependency at the same time, then collect
+                    // the (now cached) outputs below.
+                    if let Some(fork) = visitor.fork() {
+                        let branches: [Branch<'_>; $count] = [
+                            $(&|v| self.[< $param >].dependency().resolve(v).map(drop)),*
+                        ];
+                        resolve_branches(&fork, &branches)?;
+                    }
+                    Ok([<DependencyReference $count>] (
+                        $(self.[< $param >].resolve(visitor)?),*
+                    ))
+                }
+            }
+
+            impl<$([<T $param >]),*> IsDirty for [<DependencyReference $count>]<'_, $([<T $param >]),*> {
+                fn is_dirty(&self) -> bool {
+                    $(self.[< $param >].is_dirty() )||*
+                }
             }
         }
     };
@@ -1,15 +1,22 @@
 mod dep_state;
 mod dependency_edge;
+mod dependency_vec;
 mod impls;
+mod sync_dependency;
 
-use std::cell::{Ref, RefCell};
+use std::{
+    cell::{Ref, RefCell},
+    rc::Rc,
+};
 
 pub use dep_state::DependencyState;
 pub use dependency_edge::DependencyEdge;
+pub use dependency_vec::{DepRefVec, DependencyEdges, DependencyVec};
 pub use impls::*;
+pub use sync_dependency::{SyncDepRef, SyncDependency};
 
-use super::{HashValue, NodeHash, Resolve};
-use crate::execution::{error::ResolveResult, NodeState, Visitor};
+use super::{AsyncResolve, HashValue, NodeHash, Resolve};
+use crate::execution::{error::ResolveResult, NodeState, Staleness, Visitor};
 
 /// Short-hand for a reference to a single dependency.
 pub type DepRef<'a, T> = DependencyEdge<'a, Ref<'a, NodeState<T>>>;
@@ -25,17 +32,21 @@ pub struct Dependency<T> {
     dependency: T,
 }
 
-impl<T> Dependency<T>
-where
-    T: Resolve,
-    for<'a> <T as Resolve>::Output<'a>: HashValue,
-{
+impl<T> Dependency<T> {
     pub fn new(dependency: T) -> Self {
         Self {
             last_state: RefCell::new(None),
             dependency,
         }
     }
+
+    pub(crate) fn dependency(&self) -> &T {
+        &self.dependency
+    }
+
+    pub(crate) fn into_inner(self) -> T {
+        self.dependency
+    }
 }
 
 impl<T> Resolve for Dependency<T>
@@ -51,13 +62,44 @@ where
     fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
         let mut last_state = self.last_state.try_borrow_mut()?;
         let data = self.dependency.resolve(visitor)?;
-        let current_state = data.hash_value(&mut visitor.hasher());
-        if last_state.map(|s| s == current_state).unwrap_or(false) {
-            Ok(DependencyEdge::new(DependencyState::Clean, data))
-        } else {
-            *last_state = Some(current_state);
-            Ok(DependencyEdge::new(DependencyState::Dirty, data))
-        }
+        Ok(observe_edge(&mut last_state, data, visitor))
+    }
+
+    fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        self.dependency.subscribe_stale(dependee)
+    }
+}
+
+impl<T> AsyncResolve for Dependency<T>
+where
+    T: AsyncResolve,
+    for<'a> <T as AsyncResolve>::Output<'a>: HashValue,
+{
+    type Output<'a>
+        = DependencyEdge<'a, T::Output<'a>>
+    where
+        Self: 'a;
+
+    async fn resolve_async(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        let data = self.dependency.resolve_async(visitor).await?;
+        let mut last_state = self.last_state.try_borrow_mut()?;
+        Ok(observe_edge(&mut last_state, data, visitor))
+    }
+}
+
+/// Compare the hash of a resolved dependency to the one last observed,
+/// storing the latest.
+pub(crate) fn observe_edge<'a, O: HashValue>(
+    last_state: &mut Option<NodeHash>,
+    data: O,
+    visitor: &impl Visitor,
+) -> DependencyEdge<'a, O> {
+    let current_state = data.hash_value(&mut visitor.hasher());
+    if last_state.map(|s| s == current_state).unwrap_or(false) {
+        DependencyEdge::new(DependencyState::Clean, data)
+    } else {
+        *last_state = Some(current_state);
+        DependencyEdge::new(DependencyState::Dirty, data)
     }
 }
 
@@ -65,18 +107,15 @@ where
 mod tests {
     use std::rc::Rc;
 
-    use serial_test::serial;
-
     use super::*;
     use crate::execution::{
-        identifiable::reset_node_id, internal_test_utils::TestData, HashSetVisitor, InputNode,
-        IsDirty,
+        internal_test_utils::TestData, HashSetVisitor, InputNode, IsDirty, NodeIds,
     };
 
     #[test]
-    #[serial]
     fn test_dependency() {
-        reset_node_id();
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
         let node = InputNode::new(TestData::new(57));
         let dependency = Dependency::new(Rc::clone(&node));
         assert_eq!(
@@ -98,6 +137,15 @@ Dependency {
                 },
             },
         },
+        pending: Pending {
+            len: 0,
+        },
+        observers: Observers {
+            len: 0,
+        },
+        dependees: Dependees {
+            len: 0,
+        },
         id: 0,
     },
 }"#
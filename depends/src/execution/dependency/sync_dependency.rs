@@ -0,0 +1,100 @@
+use std::sync::Mutex;
+
+use super::{observe_edge, DependencyEdge};
+use crate::execution::{error::ResolveResult, HashValue, NodeHash, Resolve, SyncNodeRef, Visitor};
+
+/// Short-hand for a reference to a single thread-safe dependency.
+pub type SyncDepRef<'a, T> = DependencyEdge<'a, SyncNodeRef<'a, T>>;
+
+/// A thread-safe equivalent of a [Dependency](super::Dependency). The hash
+/// value observed when this dependency was last resolved is held behind a
+/// [Mutex], allowing dependees to be shared between threads.
+#[derive(Debug)]
+pub struct SyncDependency<T> {
+    /// The state observed of the inner dependency when it was last resolved.
+    last_state: Mutex<Option<NodeHash>>,
+    /// The wrapped node.
+    dependency: T,
+}
+
+impl<T> SyncDependency<T> {
+    pub fn new(dependency: T) -> Self {
+        Self {
+            last_state: Mutex::new(None),
+            dependency,
+        }
+    }
+
+    /// The wrapped node.
+    pub(crate) fn dependency(&self) -> &T {
+        &self.dependency
+    }
+}
+
+impl<T> Resolve for SyncDependency<T>
+where
+    T: Resolve,
+    for<'a> <T as Resolve>::Output<'a>: HashValue,
+{
+    type Output<'a>
+        = DependencyEdge<'a, T::Output<'a>>
+    where
+        Self: 'a;
+
+    fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        let data = self.dependency.resolve(visitor)?;
+        let mut last_state = self.last_state.lock()?;
+        Ok(observe_edge(&mut last_state, data, visitor))
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::execution::{
+        internal_test_utils::TestData, HashSetVisitor, IsDirty, NodeIds, SyncInputNode,
+    };
+
+    #[test]
+    fn test_sync_dependency() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let node = SyncInputNode::new(TestData::new(57));
+        let dependency = SyncDependency::new(Arc::clone(&node));
+        let mut visitor = HashSetVisitor::new();
+        {
+            let output = dependency.resolve_root(&mut visitor).unwrap();
+            assert_eq!(***output, TestData::new(57));
+            assert!(output.is_dirty());
+        }
+        {
+            let output = dependency.resolve_root(&mut visitor).unwrap();
+            assert_eq!(***output, TestData::new(57));
+            assert!(!output.is_dirty());
+        }
+        // Dependencies can be resolved from another thread.
+        let (dependency, mut visitor) = std::thread::spawn(move || {
+            {
+                let output = dependency.resolve_root(&mut visitor).unwrap();
+                assert!(!output.is_dirty());
+            }
+            (dependency, visitor)
+        })
+        .join()
+        .unwrap();
+        node.update(42).unwrap();
+        {
+            let output = dependency.resolve_root(&mut visitor).unwrap();
+            assert_eq!(
+                ***output,
+                TestData {
+                    inner: 42,
+                    recent: vec![57]
+                }
+            );
+            assert!(output.is_dirty());
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    cell::{Ref, RefCell},
+    future::Future,
+    rc::Rc,
+};
+
+pub use hrtb_workaround::AsyncIsDirtyInferenceWorkaround;
+
+use crate::execution::{
+    error::{borrow_state, borrow_state_mut, ResolveResult},
+    next_node_id,
+    node::Observers,
+    AsyncResolve, AsyncUpdateDerived, Clean, HashValue, Identifiable, IsDirty, Named, NodeChange,
+    NodeState, Visitor,
+};
+
+/// # Async Derived Node
+///
+/// A [DerivedNode](crate::DerivedNode) whose operation is asynchronous. This
+/// node is resolved with [AsyncResolve], and will await its
+/// [AsyncUpdateDerived] operation whenever its dependencies are dirty.
+///
+/// Dependencies are tracked in exactly the same way as a
+/// [DerivedNode](crate::DerivedNode), so can be any combination of input,
+/// derived or async derived nodes. Unlike a `DerivedNode`, this node isn't
+/// [marked stale](crate::Staleness), so always resolves its dependencies.
+///
+/// ```
+/// # use std::{future::Future, pin::pin, rc::Rc, sync::Arc, task::{Context, Poll, Wake, Waker}};
+/// # use depends::{AsyncDerivedNode, AsyncResolve, AsyncUpdateDerived, DepRef, Dependency, HashSetVisitor, InputNode};
+/// # use depends::derives::Operation;
+/// # use depends::error::EarlyExit;
+/// # struct NoopWaker;
+/// # impl Wake for NoopWaker {
+/// #     fn wake(self: Arc<Self>) {}
+/// # }
+/// # fn block_on<F: Future>(fut: F) -> F::Output {
+/// #     let mut fut = pin!(fut);
+/// #     let waker = Waker::from(Arc::new(NoopWaker));
+/// #     let mut cx = Context::from_waker(&waker);
+/// #     loop {
+/// #         if let Poll::Ready(res) = fut.as_mut().poll(&mut cx) {
+/// #             return res;
+/// #         }
+/// #     }
+/// # }
+/// # async fn fetch_rate() -> i64 { 3 }
+/// #[derive(Operation)]
+/// struct ApplyRate;
+///
+/// impl AsyncUpdateDerived<DepRef<'_, i64>, ApplyRate> for i64 {
+///     async fn update(&mut self, deps: DepRef<'_, i64>, _: &ApplyRate) -> Result<(), EarlyExit> {
+///         // Await some external resource.
+///         let rate = fetch_rate().await;
+///         *self = deps.data().value() * rate;
+///         Ok(())
+///     }
+/// }
+///
+/// let input = InputNode::new(14_i64);
+/// let node = AsyncDerivedNode::new(Dependency::new(Rc::clone(&input)), ApplyRate, 0_i64);
+///
+/// let mut visitor = HashSetVisitor::new();
+/// let value = block_on(async { *node.resolve_root_async(&mut visitor).await.unwrap().value() });
+/// assert_eq!(value, 42);
+/// ```
+pub struct AsyncDerivedNode<D, T, F> {
+    /// The dependencies of this node. This can be a single node, or a
+    /// struct containing multiple nodes.
+    dependencies: D,
+    /// The wrapped value of this node.
+    value: RefCell<NodeState<T>>,
+    /// Notified whenever the value changes during a resolve.
+    observers: Observers,
+    /// The unique runtime Id of this node.
+    id: usize,
+    /// The operation used to update the value, along with any parameters
+    /// it carries.
+    operation: F,
+}
+
+impl<D, T, F> AsyncDerivedNode<D, T, F>
+where
+    T: HashValue + Clean + Named,
+    F: Named,
+{
+    /// Construct this node.
+    pub fn new(dependencies: D, operation: F, value: T) -> Rc<Self> {
+        Self::new_with_id(dependencies, operation, value, next_node_id())
+    }
+
+    /// Create this node with a specified Id. Useful for tests.
+    pub fn new_with_id(dependencies: D, operation: F, value: T, id: usize) -> Rc<Self> {
+        Rc::new(Self {
+            dependencies,
+            value: RefCell::new(NodeState::new(value)),
+            observers: Observers::default(),
+            id,
+            operation,
+        })
+    }
+
+    /// Call `observer` whenever this node is recalculated and its
+    /// [NodeHash](crate::NodeHash) has changed.
+    pub fn subscribe(&self, observer: impl FnMut(&NodeChange) + 'static) {
+        self.observers.subscribe(observer);
+    }
+}
+
+impl<D, T, F> AsyncResolve for AsyncDerivedNode<D, T, F>
+where
+    for<'a> D: AsyncIsDirtyInferenceWorkaround<'a>,
+    for<'a> T: AsyncUpdateDerived<<D as AsyncIsDirtyInferenceWorkaround<'a>>::OutputWorkaround, F>,
+    T: HashValue + Clean + Named,
+    F: Named,
+{
+    type Output<'a>
+        = Ref<'a, NodeState<T>>
+    where
+        Self: 'a;
+
+    // The node is borrowed whilst its dependencies and operation are pending,
+    // in the same way as a synchronous resolve.
+    #[allow(clippy::await_holding_refcell_ref)]
+    async fn resolve_async(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        let res: ResolveResult<Self::Output<'_>> = async {
+            visitor.touch(self, Some(self.operation.label()));
+            if visitor.visit(self) {
+                let mut node_state = borrow_state_mut(&self.value)?;
+                node_state.clean();
+                let input = self
+                    .dependencies
+                    .resolve_workaround_async(visitor)
+                    .await
+                    .inspect_err(|_| self.dependencies.rollback())?;
+                if input.is_dirty() {
+                    let previous = node_state.node_hash();
+                    AsyncUpdateDerived::update(node_state.value_mut(), input, &self.operation)
+                        .await
+                        .inspect_err(|_| self.dependencies.rollback())?;
+                    // See `DerivedNode::resolve` for why this reborrow is
+                    // needed.
+                    drop(node_state);
+                    let mut node_state = borrow_state_mut(&self.value)?;
+                    node_state.mark_changed();
+                    node_state.update_node_hash(&mut visitor.hasher());
+                    let current = node_state.node_hash();
+                    drop(node_state);
+                    visitor.notify_recalculated(self);
+                    self.observers.notify(self, previous, current);
+                }
+            }
+            visitor.leave(self);
+            borrow_state(&self.value)
+        }
+        .await;
+        res.map_err(|e| e.with_node(self, Some(self.operation.label())))
+    }
+}
+
+impl<D, T: Named, F> Named for AsyncDerivedNode<D, T, F> {
+    fn name() -> &'static str {
+        T::name()
+    }
+}
+
+impl<D, T: Named, F> Identifiable for AsyncDerivedNode<D, T, F> {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+mod hrtb_workaround {
+    use super::*;
+
+    /// The [AsyncResolve] equivalent of
+    /// [IsDirtyInferenceWorkaround](super::super::IsDirtyInferenceWorkaround).
+    pub trait AsyncIsDirtyInferenceWorkaround<'a>: AsyncResolve + 'a {
+        type OutputWorkaround: IsDirty;
+
+        fn resolve_workaround_async(
+            &'a self,
+            visitor: &'a mut impl Visitor,
+        ) -> impl Future<Output = ResolveResult<Self::OutputWorkaround>>;
+    }
+
+    impl<'a, T> AsyncIsDirtyInferenceWorkaround<'a> for T
+    where
+        T: AsyncResolve + 'a,
+        <T as AsyncResolve>::Output<'a>: IsDirty,
+    {
+        type OutputWorkaround = <T as AsyncResolve>::Output<'a>;
+
+        fn resolve_workaround_async(
+            &'a self,
+            visitor: &'a mut impl Visitor,
+        ) -> impl Future<Output = ResolveResult<Self::OutputWorkaround>> {
+            self.resolve_async(visitor)
+        }
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        error::EarlyExit,
+        execution::{
+            internal_test_utils::{block_on, TestData, YieldNow},
+            Dependencies2, Dependency, DiagnosticVisitor, InputNode, NodeIds,
+        },
+        DepRef, DepRef2,
+    };
+
+    struct Fetch;
+
+    impl Named for Fetch {
+        fn name() -> &'static str {
+            "Fetch"
+        }
+    }
+
+    impl AsyncUpdateDerived<DepRef<'_, TestData>, Fetch> for TestData {
+        async fn update(&mut self, deps: DepRef<'_, TestData>, _: &Fetch) -> Result<(), EarlyExit> {
+            YieldNow::default().await;
+            if deps.data().inner == 0 {
+                return Err(EarlyExit::new("zero"));
+            }
+            self.inner = deps.data().inner * 10;
+            Ok(())
+        }
+    }
+
+    impl AsyncUpdateDerived<DepRef2<'_, TestData, TestData>, Fetch> for TestData {
+        async fn update(
+            &mut self,
+            deps: DepRef2<'_, TestData, TestData>,
+            _: &Fetch,
+        ) -> Result<(), EarlyExit> {
+            YieldNow::default().await;
+            self.inner = deps.0.data().inner + deps.1.data().inner;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_async_derived_node() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(1));
+        let other = InputNode::new(TestData::new(2));
+        let fetch =
+            AsyncDerivedNode::new(Dependency::new(Rc::clone(&input)), Fetch, TestData::new(0));
+        // Async nodes can depend on other async nodes.
+        let sum = AsyncDerivedNode::new(
+            Dependencies2::new(Rc::clone(&fetch), Rc::clone(&other)),
+            Fetch,
+            TestData::new(0),
+        );
+        let mut visitor = DiagnosticVisitor::new();
+        block_on(async {
+            {
+                let res = sum.resolve_async(&mut visitor).await.unwrap();
+                assert_eq!(res.inner, 12);
+            }
+            assert_eq!(visitor.recalculated, HashSet::from([2, 3]));
+            visitor.clear();
+
+            // Nothing has changed.
+            assert_eq!(sum.resolve_async(&mut visitor).await.unwrap().inner, 12);
+            assert_eq!(visitor.recalculated, HashSet::new());
+            visitor.clear();
+
+            other.update(3).unwrap();
+            assert_eq!(sum.resolve_async(&mut visitor).await.unwrap().inner, 13);
+            assert_eq!(visitor.recalculated, HashSet::from([3]));
+            visitor.clear();
+
+            input.update(0).unwrap();
+            assert!(matches!(
+                sum.resolve_root_async(&mut visitor).await,
+                Err(crate::error::ResolveError::EarlyExit(_))
+            ));
+        });
+    }
+}
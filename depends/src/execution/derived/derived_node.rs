@@ -1,14 +1,17 @@
 use std::{
-    cell::{Ref, RefCell},
-    marker::PhantomData,
+    cell::{BorrowMutError, Cell, Ref, RefCell, RefMut},
+    future::Future,
     rc::Rc,
 };
 
 pub use hrtb_workaround::IsDirtyInferenceWorkaround;
 
 use crate::execution::{
-    error::ResolveError, next_node_id, Clean, HashValue, Identifiable, IsDirty, Named, NodeState,
-    Resolve, UpdateDerived, Visitor,
+    error::{borrow_state, borrow_state_mut, trace_node, EarlyExit, ResolveError, ResolveResult},
+    next_node_id,
+    node::{Cutoff, Observers},
+    Arena, ArenaRef, AsyncResolve, Clean, FnOperation, HashValue, Identifiable, IsDirty, Named,
+    NodeChange, NodeState, Resolve, Staleness, UpdateDerived, UpdateFn, Visitor,
 };
 
 /// # Derived Node
@@ -41,6 +44,24 @@ use crate::execution::{
 /// > visitor is responsible for determining node hashes, and this will
 /// > not be consistent between different visitor instances.
 ///
+/// ## Skipping clean subgraphs
+///
+/// Updating an [InputNode](crate::InputNode) marks every node which depends
+/// on it [stale](Staleness). Resolving a node which isn't stale returns its
+/// value without resolving its dependencies at all, so only the parts of a
+/// graph affected by an update are walked.
+///
+/// A node is only skipped if all of its dependencies can
+/// [track](Resolve::subscribe_stale) their changes, which input, derived,
+/// [multi derived](crate::MultiDerivedNode), [Select](crate::Select) and
+/// [Dyn](crate::DynNode) nodes can. Thread-safe nodes, such as a
+/// [SyncDerivedNode](crate::SyncDerivedNode), can't. After it's
+/// recalculated, a node is resolved once more so that it can be
+/// [cleaned](Clean).
+///
+/// Tracking costs each node one extra heap allocation for its [Staleness],
+/// and each of its dependencies a weak reference to it.
+///
 /// ```
 /// # use std::cell::Ref;
 /// # use std::rc::Rc;
@@ -53,6 +74,7 @@ use crate::execution::{
 /// #    fn update(
 /// #        &mut self,
 /// #        deps: DepRef2<'_, String, String>,
+/// #        _: &Concat,
 /// #    ) -> Result<(), EarlyExit> {
 /// #         *self = format!("{} {}", deps.0.data().value(), deps.1.data().value());
 /// #         Ok(())
@@ -104,13 +126,26 @@ use crate::execution::{
 pub struct DerivedNode<D, T, F> {
     /// The dependencies of this node. This can be a single node, or a
     /// struct containing multiple nodes.
-    dependencies: D,
+    dependencies: RefCell<D>,
     /// The wrapped value of this node.
     value: RefCell<NodeState<T>>,
+    /// Notified whenever the value changes during a resolve.
+    observers: Observers,
     /// The unique runtime Id of this node.
     id: usize,
-    /// Phantom data to hold the type of the operation.
-    phantom: PhantomData<F>,
+    /// The operation used to update the value, along with any parameters
+    /// it carries.
+    operation: F,
+    /// If set, the value is compared before and after it's recalculated, and
+    /// its hash is kept if it hasn't changed.
+    cutoff: Option<Cutoff<T>>,
+    /// Whether this node may have changed since it was last resolved. This
+    /// is allocated apart from the node, so that its dependencies can hold
+    /// weak references to it however the node itself is allocated.
+    staleness: Rc<Staleness>,
+    /// Whether every dependency marks this node stale when it changes, once
+    /// they've been subscribed to.
+    tracked: Cell<Option<bool>>,
 }
 
 impl<D, T, F> DerivedNode<D, T, F>
@@ -126,15 +161,142 @@ where
     }
 
     /// Create this node with a specified Id. Useful for tests.
-    pub fn new_with_id(dependencies: D, _: F, value: T, id: usize) -> Rc<Self> {
-        // TODO: we should store `update` and make the `update_derived` call
-        //  take a &self so that values can be provided for update fns.
-        Rc::new(Self {
+    pub fn new_with_id(dependencies: D, operation: F, value: T, id: usize) -> Rc<Self> {
+        Rc::new(Self::build(dependencies, operation, value, id, None))
+    }
+
+    /// Construct this node, allocated in `arena`.
+    pub fn new_in(arena: &Arena<Self>, dependencies: D, operation: F, value: T) -> ArenaRef<Self> {
+        arena.alloc(Self::build(
             dependencies,
+            operation,
+            value,
+            next_node_id(),
+            None,
+        ))
+    }
+
+    /// Construct this node with early cutoff. Each time the value is
+    /// recalculated, it's compared to the previous value. If they're equal,
+    /// dependees will see this node as clean, even if the value can't be
+    /// hashed.
+    pub fn new_with_cutoff(dependencies: D, operation: F, value: T) -> Rc<Self>
+    where
+        T: Clone + PartialEq,
+    {
+        Self::new_with_comparator(dependencies, operation, value, T::eq)
+    }
+
+    /// Construct this node with early cutoff, using `eq` to compare the
+    /// previous and recalculated values.
+    pub fn new_with_comparator(
+        dependencies: D,
+        operation: F,
+        value: T,
+        eq: impl Fn(&T, &T) -> bool + 'static,
+    ) -> Rc<Self>
+    where
+        T: Clone,
+    {
+        let cutoff = Some(Cutoff::new(eq));
+        Rc::new(Self::build(
+            dependencies,
+            operation,
+            value,
+            next_node_id(),
+            cutoff,
+        ))
+    }
+
+    /// Construct this node with early cutoff which is safe from hash
+    /// collisions. The hash of the value is kept as a fingerprint, and only
+    /// when it matches that of the previous value are the values compared in
+    /// full. Dependees see a new version each time the value changes, rather
+    /// than its hash.
+    pub fn new_verified(dependencies: D, operation: F, value: T) -> Rc<Self>
+    where
+        T: Clone + PartialEq,
+    {
+        let cutoff = Some(Cutoff::verified(T::eq));
+        Rc::new(Self::build(
+            dependencies,
+            operation,
+            value,
+            next_node_id(),
+            cutoff,
+        ))
+    }
+
+    fn build(
+        dependencies: D,
+        operation: F,
+        value: T,
+        id: usize,
+        cutoff: Option<Cutoff<T>>,
+    ) -> Self {
+        Self {
+            dependencies: RefCell::new(dependencies),
             value: RefCell::new(NodeState::new(value)),
+            observers: Observers::default(),
             id,
-            phantom: PhantomData,
-        })
+            operation,
+            cutoff,
+            staleness: Staleness::new(),
+            tracked: Cell::new(None),
+        }
+    }
+
+    /// Mutable access to the dependencies of this node, for those which can
+    /// change between resolves, such as a
+    /// [DependencyVec](crate::DependencyVec).
+    ///
+    /// This fails if the node is currently being resolved.
+    pub fn dependencies_mut(&self) -> Result<RefMut<'_, D>, BorrowMutError> {
+        let dependencies = self.dependencies.try_borrow_mut()?;
+        // Any new dependencies are subscribed to when next resolved.
+        self.tracked.set(None);
+        self.staleness.mark();
+        Ok(dependencies)
+    }
+
+    /// Call `observer` whenever this node is recalculated and its
+    /// [NodeHash](crate::NodeHash) has changed.
+    pub fn subscribe(&self, observer: impl FnMut(&NodeChange) + 'static) {
+        self.observers.subscribe(observer);
+    }
+}
+
+impl<D, T, C> DerivedNode<D, T, FnOperation<C>>
+where
+    for<'a> D: Resolve + IsDirtyInferenceWorkaround<'a> + 'a,
+    for<'a> T: 'a,
+    C: for<'a> Fn(&mut T, <D as Resolve>::Output<'a>) -> Result<(), EarlyExit>,
+    T: UpdateFn + Named,
+{
+    /// Construct this node with a closure in place of an
+    /// [Operation](crate::derives::Operation). The node is hashed and cached
+    /// in exactly the same way, and `label` names the operation.
+    ///
+    /// ```
+    /// # use std::rc::Rc;
+    /// # use depends::{Dependencies2, DerivedNode, HashSetVisitor, InputNode, Resolve};
+    /// let a = InputNode::new(2_i64);
+    /// let b = InputNode::new(3_i64);
+    /// let node = DerivedNode::from_fn(
+    ///     Dependencies2::new(Rc::clone(&a), Rc::clone(&b)),
+    ///     "Add",
+    ///     0_i64,
+    ///     |target, deps| {
+    ///         *target = deps.0.data().value() + deps.1.data().value();
+    ///         Ok(())
+    ///     },
+    /// );
+    ///
+    /// let mut visitor = HashSetVisitor::new();
+    /// assert_eq!(*node.resolve_root(&mut visitor).unwrap().value(), 5);
+    /// ```
+    pub fn from_fn(dependencies: D, label: &'static str, value: T, f: C) -> Rc<Self> {
+        Self::new(dependencies, FnOperation::new(label, f), value)
     }
 }
 
@@ -151,28 +313,119 @@ where
         Self: 'a;
 
     fn resolve(&self, visitor: &mut impl Visitor) -> Result<Self::Output<'_>, ResolveError> {
-        visitor.touch(self, Some(F::name()));
-        if visitor.visit(self) {
-            let mut node_state = self.value.try_borrow_mut()?;
-            node_state.clean();
-            let input = self.dependencies.resolve_workaround(visitor)?;
-            if input.is_dirty() {
-                // TODO: either keep this or remove the generic impl on nodeState
-                node_state.value_mut().update(input)?;
-                // TODO: I'm running in to lifetime issues passing a
-                //  &mut node_state above, which would prevent the need to
-                //  reborrow here. For some reason, a mutable reference
-                //  causes the borrow checker to want node_state to live
-                //  beyond the current block (presumably to match input),
-                //  whereas a shared reference does not.
-                drop(node_state);
-                let mut node_state = self.value.try_borrow_mut()?;
-                node_state.update_node_hash(&mut visitor.hasher());
-                visitor.notify_recalculated(self);
+        trace_node(self, Some(self.operation.label()), || {
+            visitor.touch(self, Some(self.operation.label()));
+            if visitor.visit(self) {
+                // Dependencies must be subscribed to before this node is
+                // first resolved, so that no update is missed.
+                let tracked = self.is_tracked();
+                if self.staleness.take() || !tracked || !visitor.skip_fresh() {
+                    self.recalculate(visitor)
+                        .inspect_err(|_| self.staleness.mark())?;
+                }
+            }
+            visitor.leave(self);
+            borrow_state(&self.value)
+        })
+    }
+
+    fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        self.staleness.subscribe(dependee);
+        self.is_tracked()
+    }
+}
+
+impl<D, T, F> DerivedNode<D, T, F>
+where
+    for<'a> D: Resolve + IsDirtyInferenceWorkaround<'a> + 'a,
+    for<'a> T: UpdateDerived<<D as IsDirtyInferenceWorkaround<'a>>::OutputWorkaround, F>,
+    T: HashValue + Clean + Named,
+    F: Named,
+{
+    /// Resolve the dependencies of this node, recalculating its value if any
+    /// are dirty.
+    fn recalculate(&self, visitor: &mut impl Visitor) -> ResolveResult<()> {
+        let mut node_state = borrow_state_mut(&self.value)?;
+        node_state.clean();
+        let dependencies = self.dependencies.try_borrow()?;
+        let input = dependencies
+            .resolve_workaround(visitor)
+            .inspect_err(|_| dependencies.rollback())?;
+        if input.is_dirty() {
+            let previous = node_state.node_hash();
+            let snapshot = self
+                .cutoff
+                .as_ref()
+                .map(|cutoff| (cutoff, cutoff.snapshot(node_state.value())));
+            node_state
+                .value_mut()
+                .update(input, &self.operation)
+                .inspect_err(|_| dependencies.rollback())?;
+            // TODO: I'm running in to lifetime issues passing a
+            //  &mut node_state above, which would prevent the need to
+            //  reborrow here. For some reason, a mutable reference
+            //  causes the borrow checker to want node_state to live
+            //  beyond the current block (presumably to match input),
+            //  whereas a shared reference does not.
+            drop(node_state);
+            let mut node_state = borrow_state_mut(&self.value)?;
+            match snapshot {
+                Some((cutoff, value)) => {
+                    cutoff.update_node_hash(&mut node_state, value, &mut visitor.hasher())
+                }
+                None => {
+                    node_state.mark_changed();
+                    node_state.update_node_hash(&mut visitor.hasher())
+                }
+            }
+            let current = node_state.node_hash();
+            drop(node_state);
+            visitor.notify_recalculated(self);
+            self.observers.notify(self, previous, current);
+            // Dependees must see the new value, and this node must be
+            // resolved once more to be cleaned. An input which changed has
+            // already marked this node, and its dependees, stale on its way
+            // to being cleaned, so this only marks them when nothing else
+            // did, such as after the dependencies themselves were changed.
+            if !self.staleness.is_stale() {
+                self.staleness.mark();
             }
         }
-        visitor.leave(self);
-        Ok(self.value.try_borrow()?)
+        Ok(())
+    }
+
+    /// Whether every dependency marks this node stale when it changes, which
+    /// is decided the first time this is called.
+    fn is_tracked(&self) -> bool {
+        if let Some(tracked) = self.tracked.get() {
+            return tracked;
+        }
+        let tracked = self
+            .dependencies
+            .try_borrow()
+            .is_ok_and(|dependencies| dependencies.subscribe_stale(&self.staleness));
+        self.tracked.set(Some(tracked));
+        tracked
+    }
+}
+
+impl<D, T, F> AsyncResolve for DerivedNode<D, T, F>
+where
+    for<'a> D: Resolve + IsDirtyInferenceWorkaround<'a> + 'a,
+    for<'a> T: UpdateDerived<<D as IsDirtyInferenceWorkaround<'a>>::OutputWorkaround, F>,
+    T: HashValue + Clean + Named,
+    F: Named,
+{
+    type Output<'a>
+        = Ref<'a, NodeState<T>>
+    where
+        Self: 'a;
+
+    fn resolve_async(
+        &self,
+        visitor: &mut impl Visitor,
+    ) -> impl Future<Output = ResolveResult<Self::Output<'_>>> {
+        std::future::ready(Resolve::resolve(self, visitor))
     }
 }
 
@@ -232,3 +485,433 @@ mod hrtb_workaround {
         }
     }
 }
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::{cell::OnceCell, collections::HashSet, hash::Hasher};
+
+    use super::*;
+    use crate::{
+        error::NodeFrame,
+        execution::{internal_test_utils::TestData, DiagnosticVisitor, InputNode, NodeIds},
+        DepRef, Dependency, NodeHash,
+    };
+
+    struct Mirror;
+
+    impl Named for Mirror {
+        fn name() -> &'static str {
+            "Mirror"
+        }
+    }
+
+    impl UpdateDerived<DepRef<'_, TestData>, Mirror> for TestData {
+        fn update(&mut self, deps: DepRef<'_, TestData>, _: &Mirror) -> Result<(), EarlyExit> {
+            self.inner = deps.data().inner;
+            Ok(())
+        }
+    }
+
+    type Head = DerivedNode<Dependency<Rc<Tail>>, TestData, Mirror>;
+    type Tail = DerivedNode<Dependency<Rc<Late>>, TestData, Mirror>;
+
+    /// Resolves to a node which is only provided once the graph has been
+    /// built, making it possible to close a cycle.
+    #[derive(Default)]
+    struct Late(OnceCell<Rc<Head>>);
+
+    impl Resolve for Late {
+        type Output<'a>
+            = Ref<'a, NodeState<TestData>>
+        where
+            Self: 'a;
+
+        fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+            self.0.get().unwrap().resolve(visitor)
+        }
+    }
+
+    #[test]
+    fn test_cycle() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let late = Rc::new(Late::default());
+        let tail = DerivedNode::new(Dependency::new(Rc::clone(&late)), Mirror, TestData::new(0));
+        let head = DerivedNode::new(Dependency::new(Rc::clone(&tail)), Mirror, TestData::new(0));
+        late.0.set(Rc::clone(&head)).ok().unwrap();
+
+        let err = head.resolve_root(&mut HashSet::<usize>::new()).unwrap_err();
+        assert!(matches!(err, ResolveError::Cycle(_)));
+        let frames = [
+            NodeFrame::new(head.as_ref(), Some("Mirror")),
+            NodeFrame::new(tail.as_ref(), Some("Mirror")),
+            NodeFrame::new(head.as_ref(), Some("Mirror")),
+        ];
+        assert_eq!(err.cycle(), Some(frames.as_slice()));
+        assert_eq!(
+            err.to_string(),
+            "cycle detected: borrow error at TestData#1 (Mirror) -> TestData#0 (Mirror) -> \
+             TestData#1 (Mirror)"
+        );
+    }
+
+    #[test]
+    fn test_outstanding_borrow() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(1));
+        let node = DerivedNode::new(Dependency::new(Rc::clone(&input)), Mirror, TestData::new(0));
+        let mut visitor = HashSet::<usize>::new();
+
+        let value = input.value().unwrap();
+        let err = input.update(2).unwrap_err();
+        assert!(matches!(err, ResolveError::OutstandingBorrow(_)));
+        assert_eq!(err.node(), Some(&NodeFrame::new(input.as_ref(), None)));
+        assert_eq!(err.cycle(), None);
+        drop(value);
+
+        let value = node.resolve_root(&mut visitor).unwrap();
+        let err = node.resolve_root(&mut visitor).unwrap_err();
+        assert!(matches!(err, ResolveError::OutstandingBorrow(_)));
+        assert_eq!(
+            err.node(),
+            Some(&NodeFrame::new(node.as_ref(), Some("Mirror")))
+        );
+        assert_eq!(
+            err.to_string(),
+            "outstanding borrow: borrow mut error at TestData#1 (Mirror)"
+        );
+        drop(value);
+        assert_eq!(node.resolve_root(&mut visitor).unwrap().value().inner, 1);
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(1));
+        let node = DerivedNode::new(Dependency::new(Rc::clone(&input)), Mirror, TestData::new(0));
+        let mut visitor = HashSet::<usize>::new();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let input_sender = sender.clone();
+        input.subscribe(move |change| input_sender.send(*change).unwrap());
+        node.subscribe(move |change| sender.send(*change).unwrap());
+        let changes = || {
+            receiver
+                .try_iter()
+                .map(|c| (c.id, c.name))
+                .collect::<Vec<_>>()
+        };
+
+        drop(node.resolve_root(&mut visitor).unwrap());
+        assert_eq!(changes(), vec![(0, "TestData"), (1, "TestData")]);
+
+        // The same value doesn't change the hash of either node.
+        input.update(1).unwrap();
+        drop(node.resolve_root(&mut visitor).unwrap());
+        assert_eq!(changes(), vec![]);
+
+        input.update(2).unwrap();
+        drop(node.resolve_root(&mut visitor).unwrap());
+        let change = receiver.try_recv().unwrap();
+        assert_eq!((change.id, change.name), (0, "TestData"));
+        assert_ne!(change.previous, change.current);
+        assert_eq!(changes(), vec![(1, "TestData")]);
+    }
+
+    #[test]
+    fn test_from_fn() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(2));
+        let node = DerivedNode::from_fn(
+            Dependency::new(Rc::clone(&input)),
+            "TenTimes",
+            TestData::new(0),
+            |target, deps| {
+                if deps.data().inner > 100 {
+                    return Err(EarlyExit::new("too large"));
+                }
+                target.inner = deps.data().inner * 10;
+                Ok(())
+            },
+        );
+        let mut visitor = DiagnosticVisitor::new();
+
+        assert_eq!(node.resolve(&mut visitor).unwrap().inner, 20);
+        assert_eq!(visitor.recalculated, [1].into());
+        visitor.clear();
+
+        // Unchanged dependencies don't call the closure.
+        assert_eq!(node.resolve(&mut visitor).unwrap().inner, 20);
+        assert!(visitor.recalculated.is_empty());
+        visitor.clear();
+
+        input.update(101).unwrap();
+        let err = node.resolve(&mut visitor).unwrap_err();
+        assert_eq!(
+            err.node(),
+            Some(&NodeFrame::new(node.as_ref(), Some("TenTimes")))
+        );
+    }
+
+    /// A value which can't be hashed.
+    #[derive(Clone, PartialEq)]
+    struct Tens(u32);
+
+    impl Named for Tens {
+        fn name() -> &'static str {
+            "Tens"
+        }
+    }
+
+    impl HashValue for Tens {
+        fn hash_value(&self, _: &mut impl Hasher) -> NodeHash {
+            NodeHash::NotHashed
+        }
+    }
+
+    impl Clean for Tens {
+        fn clean(&mut self) {}
+    }
+
+    impl UpdateDerived<DepRef<'_, TestData>, Mirror> for Tens {
+        fn update(&mut self, deps: DepRef<'_, TestData>, _: &Mirror) -> Result<(), EarlyExit> {
+            self.0 = deps.data().inner / 10;
+            Ok(())
+        }
+    }
+
+    impl UpdateDerived<DepRef<'_, Tens>, Mirror> for TestData {
+        fn update(&mut self, deps: DepRef<'_, Tens>, _: &Mirror) -> Result<(), EarlyExit> {
+            self.inner = deps.data().0;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_cutoff() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(42));
+        let tens =
+            DerivedNode::new_with_cutoff(Dependency::new(Rc::clone(&input)), Mirror, Tens(0));
+        let dependee =
+            DerivedNode::new(Dependency::new(Rc::clone(&tens)), Mirror, TestData::new(0));
+        let uncut = DerivedNode::new(Dependency::new(Rc::clone(&input)), Mirror, Tens(0));
+        let uncut_dependee =
+            DerivedNode::new(Dependency::new(Rc::clone(&uncut)), Mirror, TestData::new(0));
+        let mut visitor = DiagnosticVisitor::new();
+        let resolve = |visitor: &mut DiagnosticVisitor| {
+            let values = (
+                dependee.resolve(visitor).unwrap().inner,
+                uncut_dependee.resolve(visitor).unwrap().inner,
+            );
+            let recalculated = visitor.recalculated.clone();
+            visitor.clear();
+            (values, recalculated)
+        };
+
+        assert_eq!(resolve(&mut visitor), ((4, 4), [1, 2, 3, 4].into()));
+
+        // The value can't be hashed, but hasn't changed.
+        input.update(47).unwrap();
+        assert_eq!(resolve(&mut visitor), ((4, 4), [1, 3, 4].into()));
+
+        input.update(51).unwrap();
+        assert_eq!(resolve(&mut visitor), ((5, 5), [1, 2, 3, 4].into()));
+    }
+
+    #[test]
+    fn test_comparator() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(42));
+        let node = DerivedNode::new_with_comparator(
+            Dependency::new(Rc::clone(&input)),
+            Mirror,
+            TestData::new(0),
+            |a, b| a.inner.abs_diff(b.inner) < 5,
+        );
+        let dependee =
+            DerivedNode::new(Dependency::new(Rc::clone(&node)), Mirror, TestData::new(0));
+        let mut visitor = HashSet::<usize>::new();
+
+        assert_eq!(dependee.resolve_root(&mut visitor).unwrap().inner, 42);
+        // Close enough to the previous value to be considered unchanged.
+        input.update(45).unwrap();
+        assert_eq!(dependee.resolve_root(&mut visitor).unwrap().inner, 42);
+        assert_eq!(node.value.borrow().inner, 45);
+        input.update(50).unwrap();
+        assert_eq!(dependee.resolve_root(&mut visitor).unwrap().inner, 50);
+    }
+
+    /// A value whose hash always collides.
+    #[derive(Clone, PartialEq)]
+    struct Colliding(u32);
+
+    impl Named for Colliding {
+        fn name() -> &'static str {
+            "Colliding"
+        }
+    }
+
+    impl HashValue for Colliding {
+        fn hash_value(&self, _: &mut impl Hasher) -> NodeHash {
+            NodeHash::Hashed(0)
+        }
+    }
+
+    impl Clean for Colliding {
+        fn clean(&mut self) {}
+    }
+
+    impl UpdateDerived<DepRef<'_, TestData>, Mirror> for Colliding {
+        fn update(&mut self, deps: DepRef<'_, TestData>, _: &Mirror) -> Result<(), EarlyExit> {
+            self.0 = deps.data().inner / 10;
+            Ok(())
+        }
+    }
+
+    impl UpdateDerived<DepRef<'_, Colliding>, Mirror> for TestData {
+        fn update(&mut self, deps: DepRef<'_, Colliding>, _: &Mirror) -> Result<(), EarlyExit> {
+            self.inner = deps.data().0;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_verified() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(42));
+        let verified =
+            DerivedNode::new_verified(Dependency::new(Rc::clone(&input)), Mirror, Colliding(0));
+        let dependee = DerivedNode::new(
+            Dependency::new(Rc::clone(&verified)),
+            Mirror,
+            TestData::new(0),
+        );
+        let hashed = DerivedNode::new(Dependency::new(Rc::clone(&input)), Mirror, Colliding(0));
+        let hashed_dependee = DerivedNode::new(
+            Dependency::new(Rc::clone(&hashed)),
+            Mirror,
+            TestData::new(0),
+        );
+        let mut visitor = DiagnosticVisitor::new();
+        let mut resolve = || {
+            let values = (
+                dependee.resolve(&mut visitor).unwrap().inner,
+                hashed_dependee.resolve(&mut visitor).unwrap().inner,
+            );
+            visitor.clear();
+            values
+        };
+
+        assert_eq!(resolve(), (4, 4));
+        // Unchanged values are still cut off.
+        input.update(47).unwrap();
+        assert_eq!(resolve(), (4, 4));
+        // The collision causes the hashed dependee to serve a stale value.
+        input.update(51).unwrap();
+        assert_eq!(resolve(), (5, 4));
+    }
+
+    #[test]
+    fn test_skip_fresh() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let left = InputNode::new(TestData::new(1));
+        let right = InputNode::new(TestData::new(2));
+        let left_node =
+            DerivedNode::new(Dependency::new(Rc::clone(&left)), Mirror, TestData::new(0));
+        let right_node =
+            DerivedNode::new(Dependency::new(Rc::clone(&right)), Mirror, TestData::new(0));
+        let mut visitor = DiagnosticVisitor::new();
+        type Node = DerivedNode<Dependency<Rc<InputNode<TestData>>>, TestData, Mirror>;
+        let mut resolve = |node: &Node| {
+            let value = node.resolve(&mut visitor).unwrap().inner;
+            let visited = visitor.visitor.clone();
+            visitor.clear();
+            (value, visited)
+        };
+
+        assert_eq!(resolve(&left_node), (1, [0, 2].into()));
+        // The input is resolved again to be cleaned.
+        assert_eq!(resolve(&left_node), (1, [0, 2].into()));
+        assert_eq!(resolve(&left_node), (1, [2].into()));
+
+        // Updating an unrelated input doesn't make this node stale.
+        right.update(3).unwrap();
+        assert_eq!(resolve(&left_node), (1, [2].into()));
+        assert_eq!(resolve(&right_node), (3, [1, 3].into()));
+
+        left.update(4).unwrap();
+        assert_eq!(resolve(&left_node), (4, [0, 2].into()));
+        // The input marked the node stale again, to be cleaned.
+        assert!(left_node.staleness.is_stale());
+        // Enqueued updates mark dependees stale, too.
+        left.enqueue(5);
+        assert_eq!(resolve(&left_node), (5, [0, 2].into()));
+    }
+
+    #[test]
+    fn test_skip_fresh_not_hashed() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(42));
+        let tens = DerivedNode::new(Dependency::new(Rc::clone(&input)), Mirror, Tens(0));
+        let dependee =
+            DerivedNode::new(Dependency::new(Rc::clone(&tens)), Mirror, TestData::new(0));
+        let mut visitor = DiagnosticVisitor::new();
+        let mut resolve = || {
+            let value = dependee.resolve(&mut visitor).unwrap().inner;
+            let recalculated = visitor.recalculated.clone();
+            visitor.clear();
+            (value, recalculated)
+        };
+
+        assert_eq!(resolve(), (4, [1, 2].into()));
+        // A dependency which can't be hashed is always dirty, so its dependee
+        // is never skipped.
+        assert_eq!(resolve(), (4, [2].into()));
+        assert_eq!(resolve(), (4, [2].into()));
+        input.update(51).unwrap();
+        assert_eq!(resolve(), (5, [1, 2].into()));
+        assert_eq!(resolve(), (5, [2].into()));
+    }
+
+    /// Resolves an input without tracking its changes.
+    struct Untracked(Rc<InputNode<TestData>>);
+
+    impl Resolve for Untracked {
+        type Output<'a>
+            = Ref<'a, NodeState<TestData>>
+        where
+            Self: 'a;
+
+        fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+            self.0.resolve(visitor)
+        }
+    }
+
+    #[test]
+    fn test_untracked() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(1));
+        let untracked = Untracked(Rc::clone(&input));
+        let node = DerivedNode::new(Dependency::new(untracked), Mirror, TestData::new(0));
+        let mut visitor = DiagnosticVisitor::new();
+
+        // The node is never skipped, as its dependency can't mark it stale.
+        for _ in 0..4 {
+            assert_eq!(node.resolve(&mut visitor).unwrap().inner, 1);
+            assert!(visitor.visitor.contains(&input.id()));
+            visitor.clear();
+        }
+        input.update(2).unwrap();
+        assert_eq!(node.resolve(&mut visitor).unwrap().inner, 2);
+    }
+}
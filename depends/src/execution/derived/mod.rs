@@ -1,3 +1,11 @@
+mod async_derived_node;
 mod derived_node;
+mod multi_derived_node;
+mod outputs;
+mod sync_derived_node;
 
-pub use derived_node::DerivedNode;
+pub use async_derived_node::AsyncDerivedNode;
+pub use derived_node::{DerivedNode, IsDirtyInferenceWorkaround};
+pub use multi_derived_node::{MultiDerivedNode, OutputNode};
+pub use outputs::*;
+pub use sync_derived_node::SyncDerivedNode;
@@ -0,0 +1,495 @@
+use std::{
+    cell::{Cell, Ref, RefCell},
+    future::Future,
+    rc::Rc,
+};
+
+use super::{IsDirtyInferenceWorkaround, OutputAt, Outputs};
+use crate::execution::{
+    error::{borrow_state, borrow_state_mut, trace_node, ResolveResult},
+    next_node_id,
+    node::Observers,
+    AsyncResolve, Identifiable, IsDirty, Named, NodeChange, NodeState, Resolve, Staleness,
+    UpdateDerived, Visitor,
+};
+
+/// # Multi Derived Node
+///
+/// A [DerivedNode](crate::DerivedNode) whose operation fills several outputs
+/// in a single pass. Each output is hashed separately, and is depended upon
+/// through its own [OutputNode], so dependees of one output are not dirtied
+/// when only the others change.
+///
+/// The outputs are held in one of `Outputs2..8`, each field of which is the
+/// [NodeState] of an output.
+///
+/// Like a [DerivedNode](crate::DerivedNode), this node skips resolving its
+/// dependencies when it hasn't been marked [stale](Staleness). Dependees of
+/// any one output are marked stale whenever this node is.
+///
+/// ```
+/// # use std::rc::Rc;
+/// # use depends::{DepRef, Dependency, DerivedNode, DiagnosticVisitor, Identifiable, InputNode, MultiDerivedNode, Outputs2, Resolve, UpdateDerived, Visitor};
+/// # use depends::derives::Operation;
+/// # use depends::error::EarlyExit;
+/// #[derive(Operation)]
+/// struct DivRem;
+///
+/// impl UpdateDerived<DepRef<'_, i64>, DivRem> for Outputs2<i64, i64> {
+///     fn update(&mut self, deps: DepRef<'_, i64>, _: &DivRem) -> Result<(), EarlyExit> {
+///         let value = *deps.data().value();
+///         *self.0 = value / 10;
+///         *self.1 = value % 10;
+///         Ok(())
+///     }
+/// }
+/// # #[derive(Operation)]
+/// # struct Double;
+/// # impl UpdateDerived<DepRef<'_, i64>, Double> for i64 {
+/// #     fn update(&mut self, deps: DepRef<'_, i64>, _: &Double) -> Result<(), EarlyExit> {
+/// #         *self = deps.data().value() * 2;
+/// #         Ok(())
+/// #     }
+/// # }
+///
+/// let input = InputNode::new(42_i64);
+/// let div_rem = MultiDerivedNode::new(
+///     Dependency::new(Rc::clone(&input)),
+///     DivRem,
+///     Outputs2::new(0, 0),
+/// );
+/// let rem = div_rem.output::<1>();
+/// let double_div = DerivedNode::new(Dependency::new(div_rem.output::<0>()), Double, 0_i64);
+///
+/// let mut visitor = DiagnosticVisitor::new();
+/// assert_eq!(*rem.resolve(&mut visitor).unwrap().value(), 2);
+/// assert_eq!(*double_div.resolve(&mut visitor).unwrap().value(), 8);
+/// visitor.clear();
+///
+/// // Only the remainder changes, so `double_div` is not recalculated.
+/// input.update(47).unwrap();
+/// assert_eq!(*double_div.resolve(&mut visitor).unwrap().value(), 8);
+/// assert!(!visitor.recalculated.contains(&double_div.id()));
+/// assert_eq!(*rem.resolve(&mut visitor).unwrap().value(), 7);
+/// ```
+pub struct MultiDerivedNode<D, T, F> {
+    /// The dependencies of this node. This can be a single node, or a
+    /// struct containing multiple nodes.
+    dependencies: D,
+    /// The outputs of this node.
+    value: RefCell<T>,
+    /// Notified whenever an output changes during a resolve.
+    observers: Observers,
+    /// The unique runtime Id of this node.
+    id: usize,
+    /// The operation used to update the outputs.
+    operation: F,
+    /// The Id of each [OutputNode], in order. These are allocated with the
+    /// node, so every handle to the same output shares its Id.
+    output_ids: Vec<usize>,
+    /// Whether this node may have changed since it was last resolved.
+    staleness: Rc<Staleness>,
+    /// Whether every dependency marks this node stale when it changes, once
+    /// they've been subscribed to.
+    tracked: Cell<Option<bool>>,
+}
+
+impl<D, T, F> MultiDerivedNode<D, T, F>
+where
+    for<'a> D: Resolve + IsDirtyInferenceWorkaround<'a> + 'a,
+    for<'a> T: UpdateDerived<<D as Resolve>::Output<'a>, F> + 'a,
+    T: Outputs + Named,
+    F: Named,
+{
+    /// Construct this node.
+    pub fn new(dependencies: D, operation: F, value: T) -> Rc<Self> {
+        Self::new_with_id(dependencies, operation, value, next_node_id())
+    }
+
+    /// Create this node with a specified Id. Useful for tests.
+    pub fn new_with_id(dependencies: D, operation: F, value: T, id: usize) -> Rc<Self> {
+        let output_ids = value.node_hashes().iter().map(|_| next_node_id()).collect();
+        Rc::new(Self {
+            dependencies,
+            value: RefCell::new(value),
+            observers: Observers::default(),
+            id,
+            operation,
+            output_ids,
+            staleness: Staleness::new(),
+            tracked: Cell::new(None),
+        })
+    }
+
+    /// Call `observer` whenever this node is recalculated, once for each
+    /// output whose [NodeHash](crate::NodeHash) has changed. The index of
+    /// the output is given by [NodeChange::output].
+    pub fn subscribe(&self, observer: impl FnMut(&NodeChange) + 'static) {
+        self.observers.subscribe(observer);
+    }
+
+    /// A node which resolves to the output at index `I`. Every call for the
+    /// same index gives a handle with the same Id, so visitors treat them as
+    /// one node.
+    pub fn output<const I: usize>(self: &Rc<Self>) -> Rc<OutputNode<D, T, F, I>>
+    where
+        T: OutputAt<I>,
+    {
+        Rc::new(OutputNode {
+            node: Rc::clone(self),
+            id: self.output_ids[I],
+        })
+    }
+}
+
+impl<D, T, F> Resolve for MultiDerivedNode<D, T, F>
+where
+    for<'a> D: Resolve + IsDirtyInferenceWorkaround<'a> + 'a,
+    for<'a> T: UpdateDerived<<D as IsDirtyInferenceWorkaround<'a>>::OutputWorkaround, F>,
+    T: Outputs + Named,
+    F: Named,
+{
+    type Output<'a>
+        = Ref<'a, T>
+    where
+        Self: 'a;
+
+    fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        trace_node(self, Some(self.operation.label()), || {
+            visitor.touch(self, Some(self.operation.label()));
+            if visitor.visit(self) {
+                // See `DerivedNode::resolve`.
+                let tracked = self.is_tracked();
+                if self.staleness.take() || !tracked || !visitor.skip_fresh() {
+                    self.recalculate(visitor)
+                        .inspect_err(|_| self.staleness.mark())?;
+                }
+            }
+            visitor.leave(self);
+            borrow_state(&self.value)
+        })
+    }
+
+    fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        self.staleness.subscribe(dependee);
+        self.is_tracked()
+    }
+}
+
+impl<D, T, F> MultiDerivedNode<D, T, F>
+where
+    for<'a> D: Resolve + IsDirtyInferenceWorkaround<'a> + 'a,
+    for<'a> T: UpdateDerived<<D as IsDirtyInferenceWorkaround<'a>>::OutputWorkaround, F>,
+    T: Outputs + Named,
+    F: Named,
+{
+    /// Resolve the dependencies of this node, recalculating its outputs if
+    /// any are dirty.
+    fn recalculate(&self, visitor: &mut impl Visitor) -> ResolveResult<()> {
+        let mut outputs = borrow_state_mut(&self.value)?;
+        outputs.clean();
+        let input = self
+            .dependencies
+            .resolve_workaround(visitor)
+            .inspect_err(|_| self.dependencies.rollback())?;
+        if input.is_dirty() {
+            // Hashes are only collected if anything is observing them.
+            let previous = (!self.observers.is_empty()).then(|| outputs.node_hashes());
+            outputs
+                .update(input, &self.operation)
+                .inspect_err(|_| self.dependencies.rollback())?;
+            // See `DerivedNode::resolve` for why this reborrow is needed.
+            drop(outputs);
+            let mut outputs = borrow_state_mut(&self.value)?;
+            outputs.update_node_hashes(visitor);
+            let current = previous.is_some().then(|| outputs.node_hashes());
+            drop(outputs);
+            visitor.notify_recalculated(self);
+            let changes = previous
+                .into_iter()
+                .flatten()
+                .zip(current.into_iter().flatten());
+            for (output, (previous, current)) in changes.enumerate() {
+                self.observers
+                    .notify_output(self, Some(output), previous, current);
+            }
+            // See `DerivedNode::recalculate`.
+            if !self.staleness.is_stale() {
+                self.staleness.mark();
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether every dependency marks this node stale when it changes, which
+    /// is decided the first time this is called.
+    fn is_tracked(&self) -> bool {
+        if let Some(tracked) = self.tracked.get() {
+            return tracked;
+        }
+        let tracked = self.dependencies.subscribe_stale(&self.staleness);
+        self.tracked.set(Some(tracked));
+        tracked
+    }
+}
+
+impl<D, T: Named, F> Named for MultiDerivedNode<D, T, F> {
+    fn name() -> &'static str {
+        T::name()
+    }
+}
+
+impl<D, T: Named, F> Identifiable for MultiDerivedNode<D, T, F> {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+/// # Output Node
+///
+/// A single output of a [MultiDerivedNode]. This can be depended upon like
+/// any other node, and is only dirty when its own output has changed.
+pub struct OutputNode<D, T, F, const I: usize> {
+    /// The node which calculates this output.
+    node: Rc<MultiDerivedNode<D, T, F>>,
+    /// The unique runtime Id of this node.
+    id: usize,
+}
+
+impl<D, T, F, const I: usize> Resolve for OutputNode<D, T, F, I>
+where
+    for<'a> D: Resolve + IsDirtyInferenceWorkaround<'a> + 'a,
+    for<'a> T: UpdateDerived<<D as IsDirtyInferenceWorkaround<'a>>::OutputWorkaround, F>,
+    T: Outputs + Named + OutputAt<I>,
+    T::Value: Named,
+    F: Named,
+{
+    type Output<'a>
+        = Ref<'a, NodeState<T::Value>>
+    where
+        Self: 'a;
+
+    fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        let operation = self.node.operation.label();
+        trace_node(self, Some(operation), || {
+            visitor.touch(self, Some(operation));
+            let outputs = self.node.resolve(visitor)?;
+            visitor.leave(self);
+            Ok(Ref::map(outputs, OutputAt::<I>::output))
+        })
+    }
+
+    fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        self.node.subscribe_stale(dependee)
+    }
+}
+
+impl<D, T, F, const I: usize> AsyncResolve for OutputNode<D, T, F, I>
+where
+    for<'a> D: Resolve + IsDirtyInferenceWorkaround<'a> + 'a,
+    for<'a> T: UpdateDerived<<D as IsDirtyInferenceWorkaround<'a>>::OutputWorkaround, F>,
+    T: Outputs + Named + OutputAt<I>,
+    T::Value: Named,
+    F: Named,
+{
+    type Output<'a>
+        = Ref<'a, NodeState<T::Value>>
+    where
+        Self: 'a;
+
+    fn resolve_async(
+        &self,
+        visitor: &mut impl Visitor,
+    ) -> impl Future<Output = ResolveResult<Self::Output<'_>>> {
+        std::future::ready(Resolve::resolve(self, visitor))
+    }
+}
+
+impl<D, T: OutputAt<I>, F, const I: usize> Named for OutputNode<D, T, F, I>
+where
+    T::Value: Named,
+{
+    fn name() -> &'static str {
+        T::Value::name()
+    }
+}
+
+impl<D, T: OutputAt<I>, F, const I: usize> Identifiable for OutputNode<D, T, F, I>
+where
+    T::Value: Named,
+{
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{EarlyExit, NodeFrame},
+        execution::{internal_test_utils::TestData, DiagnosticVisitor, InputNode, NodeIds},
+        DepRef, Dependency, DerivedNode, Outputs2,
+    };
+
+    struct Split;
+
+    impl Named for Split {
+        fn name() -> &'static str {
+            "Split"
+        }
+    }
+
+    impl UpdateDerived<DepRef<'_, TestData>, Split> for Outputs2<TestData, TestData> {
+        fn update(&mut self, deps: DepRef<'_, TestData>, _: &Split) -> Result<(), EarlyExit> {
+            self.0.inner = deps.data().inner / 10;
+            self.1.inner = deps.data().inner % 10;
+            Ok(())
+        }
+    }
+
+    struct Mirror;
+
+    impl Named for Mirror {
+        fn name() -> &'static str {
+            "Mirror"
+        }
+    }
+
+    impl UpdateDerived<DepRef<'_, TestData>, Mirror> for TestData {
+        fn update(&mut self, deps: DepRef<'_, TestData>, _: &Mirror) -> Result<(), EarlyExit> {
+            self.inner = deps.data().inner;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_multi_derived_node() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(42));
+        let split = MultiDerivedNode::new(
+            Dependency::new(Rc::clone(&input)),
+            Split,
+            Outputs2::new(TestData::new(0), TestData::new(0)),
+        );
+        let tens = DerivedNode::new(
+            Dependency::new(split.output::<0>()),
+            Mirror,
+            TestData::new(0),
+        );
+        let units = DerivedNode::new(
+            Dependency::new(split.output::<1>()),
+            Mirror,
+            TestData::new(0),
+        );
+        assert_eq!((tens.id(), units.id()), (4, 5));
+        let mut visitor = DiagnosticVisitor::new();
+        let resolve = |visitor: &mut DiagnosticVisitor| {
+            (
+                tens.resolve(visitor).unwrap().inner,
+                units.resolve(visitor).unwrap().inner,
+            )
+        };
+
+        assert_eq!(resolve(&mut visitor), (4, 2));
+        assert_eq!(visitor.recalculated, [1, 4, 5].into());
+        visitor.clear();
+
+        // Only the units have changed.
+        input.update(47).unwrap();
+        assert_eq!(resolve(&mut visitor), (4, 7));
+        assert_eq!(visitor.recalculated, [1, 5].into());
+        visitor.clear();
+
+        input.update(57).unwrap();
+        assert_eq!(resolve(&mut visitor), (5, 7));
+        assert_eq!(visitor.recalculated, [1, 4].into());
+    }
+
+    #[test]
+    fn test_output_handles() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(42));
+        let split = MultiDerivedNode::new(
+            Dependency::new(Rc::clone(&input)),
+            Split,
+            Outputs2::new(TestData::new(0), TestData::new(0)),
+        );
+        let tens = split.output::<0>();
+        assert_eq!(tens.id(), split.output::<0>().id());
+        assert_eq!((tens.id(), split.output::<1>().id()), (2, 3));
+
+        let mut visitor = DiagnosticVisitor::new();
+        let outputs = split.resolve(&mut visitor).unwrap();
+        visitor.clear();
+        let err = tens.resolve(&mut visitor).unwrap_err();
+        let frames = [
+            NodeFrame::new(tens.as_ref(), Some("Split")),
+            NodeFrame::new(split.as_ref(), Some("Split")),
+        ];
+        assert_eq!(err.trace(), frames.as_slice());
+        drop(outputs);
+    }
+
+    #[test]
+    fn test_multi_subscribe() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(42));
+        let split = MultiDerivedNode::new(
+            Dependency::new(Rc::clone(&input)),
+            Split,
+            Outputs2::new(TestData::new(0), TestData::new(0)),
+        );
+        let (sender, receiver) = std::sync::mpsc::channel();
+        split.subscribe(move |change| sender.send((change.id, change.output)).unwrap());
+        let mut visitor = DiagnosticVisitor::new();
+
+        drop(split.resolve(&mut visitor).unwrap());
+        let changes = receiver.try_iter().collect::<Vec<_>>();
+        assert_eq!(changes, vec![(1, Some(0)), (1, Some(1))]);
+
+        // Only the units have changed.
+        input.update(47).unwrap();
+        visitor.clear();
+        drop(split.resolve(&mut visitor).unwrap());
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![(1, Some(1))]);
+    }
+
+    #[test]
+    fn test_multi_skip_fresh() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(42));
+        let split = MultiDerivedNode::new(
+            Dependency::new(Rc::clone(&input)),
+            Split,
+            Outputs2::new(TestData::new(0), TestData::new(0)),
+        );
+        let tens = DerivedNode::new(
+            Dependency::new(split.output::<0>()),
+            Mirror,
+            TestData::new(0),
+        );
+        let mut visitor = DiagnosticVisitor::new();
+        let mut resolve = || {
+            let value = tens.resolve(&mut visitor).unwrap().inner;
+            let visited = visitor.visitor.clone();
+            visitor.clear();
+            (value, visited)
+        };
+
+        assert_eq!(resolve(), (4, [0, 1, 4].into()));
+        // Resolved again to be cleaned, after which the outputs are tracked.
+        assert_eq!(resolve(), (4, [0, 1, 4].into()));
+        assert_eq!(resolve(), (4, [4].into()));
+
+        // Dependees of every output are marked stale.
+        input.update(47).unwrap();
+        assert_eq!(resolve(), (4, [0, 1, 4].into()));
+        input.update(57).unwrap();
+        assert_eq!(resolve(), (5, [0, 1, 4].into()));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::execution::{Clean, HashValue, Named, NodeHash, NodeState, Visitor};
+
+/// The outputs of a [MultiDerivedNode](crate::MultiDerivedNode), each of which
+/// is hashed separately.
+pub trait Outputs: Clean {
+    /// Update the [NodeHash](crate::NodeHash) of every output, after they have
+    /// been recalculated.
+    fn update_node_hashes(&mut self, visitor: &impl Visitor);
+
+    /// The [NodeHash] of every output, in order.
+    fn node_hashes(&self) -> Vec<NodeHash>;
+}
+
+/// Access to the output at index `I` of a group of [Outputs].
+pub trait OutputAt<const I: usize> {
+    type Value;
+
+    fn output(&self) -> &NodeState<Self::Value>;
+}
+
+/// Implement [OutputAt] for the output at each index.
+macro_rules! generate_output_at {
+    ($outputs:ident, $generics:tt, [$($param:tt),*]) => {
+        $(generate_output_at!(@index $outputs, $generics, $param);)*
+    };
+    (@index $outputs:ident, [$($generic:ident),*], $param:tt) => {
+        paste::paste! {
+            impl<$($generic),*> OutputAt<$param> for $outputs<$($generic),*> {
+                type Value = [<T $param>];
+
+                fn output(&self) -> &NodeState<Self::Value> {
+                    &self.$param
+                }
+            }
+        }
+    };
+}
+
+macro_rules! generate_outputs {
+    ($count:expr, $($param:tt),*) => {
+        paste::paste! {
+            pub struct [<Outputs $count>]<$([<T $param >]),*> (
+                $(pub NodeState<[<T $param >]>,)*
+            );
+
+            impl<$([<T $param >]: HashValue),*> [<Outputs $count>]<$([<T $param >]),*> {
+                #[allow(clippy::too_many_arguments)]
+                pub fn new($([<t $param >]: [<T $param >]),*) -> Self {
+                    Self (
+                        $(NodeState::new([<t $param >])),*
+                    )
+                }
+            }
+
+            impl<$([<T $param >]),*> Named for [<Outputs $count>]<$([<T $param >]),*> {
+                fn name() -> &'static str {
+                     stringify!([<Outputs $count>])
+                }
+            }
+
+            impl<$([<T $param >]: Clean),*> Clean for [<Outputs $count>]<$([<T $param >]),*> {
+                fn clean(&mut self) {
+                    $(self.$param.clean();)*
+                }
+            }
+
+            impl<$([<T $param >]: HashValue + Clean),*> Outputs for [<Outputs $count>]<$([<T $param >]),*> {
+                fn update_node_hashes(&mut self, visitor: &impl Visitor) {
+                    $(
+                        self.$param.mark_changed();
+                        self.$param.update_node_hash(&mut visitor.hasher());
+                    )*
+                }
+
+                fn node_hashes(&self) -> Vec<NodeHash> {
+                    vec![$(self.$param.node_hash()),*]
+                }
+            }
+
+            generate_output_at!([<Outputs $count>], [$([<T $param >]),*], [$($param),*]);
+        }
+    };
+}
+
+generate_outputs!(2, 0, 1);
+generate_outputs!(3, 0, 1, 2);
+generate_outputs!(4, 0, 1, 2, 3);
+generate_outputs!(5, 0, 1, 2, 3, 4);
+generate_outputs!(6, 0, 1, 2, 3, 4, 5);
+generate_outputs!(7, 0, 1, 2, 3, 4, 5, 6);
+generate_outputs!(8, 0, 1, 2, 3, 4, 5, 6, 7);
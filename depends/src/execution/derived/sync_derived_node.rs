@@ -0,0 +1,472 @@
+use std::{
+    sync::{Arc, Condvar, Mutex, RwLock, RwLockReadGuard},
+    thread::{self, ThreadId},
+};
+
+use super::IsDirtyInferenceWorkaround;
+use crate::execution::{
+    error::{lock_cycle, trace_node, ResolveError, ResolveResult},
+    next_node_id,
+    node::SyncObservers,
+    Clean, HashValue, Identifiable, IsDirty, Named, NodeChange, NodeState, Resolve, UpdateDerived,
+    Visitor,
+};
+
+/// # Sync Derived Node
+///
+/// A thread-safe equivalent of a [DerivedNode](crate::DerivedNode). The
+/// wrapped value is held behind an [RwLock] and the node is constructed in an
+/// [Arc]. Use [SyncDependency](crate::SyncDependency) or
+/// `SyncDependencies2..16` to describe its dependencies.
+///
+/// Thread-safe dependencies resolve to [SyncDepRef](crate::SyncDepRef)s
+/// rather than [DepRef](crate::DepRef)s. Both are a
+/// [DependencyEdge](crate::DependencyEdge) to a read-reference of the
+/// dependency's [NodeState], so an [UpdateDerived] implementation which is
+/// generic over that reference can be shared by both families of node, as
+/// below.
+///
+/// If another thread is resolving this node, resolving it will wait for that
+/// thread to finish.
+///
+/// Unlike a [DerivedNode](crate::DerivedNode), this node can't
+/// [track](Resolve::subscribe_stale) its changes, as marking nodes stale
+/// isn't thread-safe. It always resolves its dependencies, as do any nodes
+/// depending on it.
+///
+/// ```
+/// # use std::{ops::Deref, rc::Rc, sync::Arc};
+/// # use depends::{Dependencies2, DependencyReference2, DerivedNode, HashSetVisitor, InputNode, NodeState, SyncDependencies2, SyncDerivedNode, SyncInputNode, Resolve, UpdateDerived};
+/// # use depends::derives::Operation;
+/// # use depends::error::EarlyExit;
+/// #[derive(Operation)]
+/// struct Multiply;
+///
+/// impl<A, B> UpdateDerived<DependencyReference2<'_, A, B>, Multiply> for i64
+/// where
+///     A: Deref<Target = NodeState<i64>>,
+///     B: Deref<Target = NodeState<i64>>,
+/// {
+///     fn update(
+///         &mut self,
+///         deps: DependencyReference2<'_, A, B>,
+///         _: &Multiply,
+///     ) -> Result<(), EarlyExit> {
+///         *self = deps.0.data().value() * deps.1.data().value();
+///         Ok(())
+///     }
+/// }
+///
+/// let a = SyncInputNode::new(6_i64);
+/// let b = SyncInputNode::new(7_i64);
+/// let node = SyncDerivedNode::new(
+///     SyncDependencies2::new(Arc::clone(&a), Arc::clone(&b)),
+///     Multiply,
+///     0_i64,
+/// );
+///
+/// // The graph can be resolved on another thread.
+/// let handle = std::thread::spawn({
+///     let node = Arc::clone(&node);
+///     move || {
+///         let mut visitor = HashSetVisitor::new();
+///         let value = *node.resolve_root(&mut visitor).unwrap().value();
+///         value
+///     }
+/// });
+/// assert_eq!(handle.join().unwrap(), 42);
+///
+/// // The same operation works with nodes which aren't thread-safe.
+/// let node = DerivedNode::new(
+///     Dependencies2::new(InputNode::new(6_i64), InputNode::new(7_i64)),
+///     Multiply,
+///     0_i64,
+/// );
+/// let mut visitor = HashSetVisitor::new();
+/// assert_eq!(*node.resolve_root(&mut visitor).unwrap().value(), 42);
+/// ```
+pub struct SyncDerivedNode<D, T, F> {
+    /// The dependencies of this node. This can be a single node, or a
+    /// struct containing multiple nodes.
+    dependencies: D,
+    /// The wrapped value of this node.
+    value: RwLock<NodeState<T>>,
+    /// Held by the thread resolving this node.
+    resolving: ResolveLock,
+    /// Notified whenever the value changes during a resolve.
+    observers: SyncObservers,
+    /// The unique runtime Id of this node.
+    id: usize,
+    /// The operation used to update the value, along with any parameters
+    /// it carries.
+    operation: F,
+}
+
+/// Ensures only one thread resolves a [SyncDerivedNode] at a time. This is
+/// held from before the value is recalculated until a read-reference to it
+/// has been taken, so no other thread can recalculate it in between.
+#[derive(Debug, Default)]
+struct ResolveLock {
+    /// The thread currently resolving the node, if any.
+    owner: Mutex<Option<ThreadId>>,
+    /// Signalled whenever the lock is released.
+    released: Condvar,
+}
+
+impl ResolveLock {
+    /// Wait for any other thread to finish resolving the node. If the current
+    /// thread holds the lock, the node is part of a cycle, so this fails
+    /// rather than waiting forever.
+    fn acquire(&self) -> ResolveResult<ResolveGuard<'_>> {
+        let current = thread::current().id();
+        let mut owner = self.owner.lock()?;
+        loop {
+            match *owner {
+                None => {
+                    *owner = Some(current);
+                    return Ok(ResolveGuard(self));
+                }
+                Some(id) if id == current => return Err(lock_cycle(None)),
+                Some(_) => owner = self.released.wait(owner)?,
+            }
+        }
+    }
+
+    /// Fail with a cycle if the current thread holds the lock.
+    fn check_reentry(&self) -> ResolveResult<()> {
+        if *self.owner.lock()? == Some(thread::current().id()) {
+            return Err(lock_cycle(None));
+        }
+        Ok(())
+    }
+}
+
+/// Releases a [ResolveLock] when dropped.
+struct ResolveGuard<'a>(&'a ResolveLock);
+
+impl Drop for ResolveGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.owner.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.0.released.notify_all();
+    }
+}
+
+impl<D, T, F> SyncDerivedNode<D, T, F>
+where
+    for<'a> D: Resolve + IsDirtyInferenceWorkaround<'a> + 'a,
+    for<'a> T: UpdateDerived<<D as Resolve>::Output<'a>, F> + 'a,
+    T: HashValue + Clean + Named,
+    F: Named,
+{
+    /// Construct this node.
+    pub fn new(dependencies: D, operation: F, value: T) -> Arc<Self> {
+        Self::new_with_id(dependencies, operation, value, next_node_id())
+    }
+
+    /// Create this node with a specified Id. Useful for tests.
+    pub fn new_with_id(dependencies: D, operation: F, value: T, id: usize) -> Arc<Self> {
+        Arc::new(Self {
+            dependencies,
+            value: RwLock::new(NodeState::new(value)),
+            resolving: ResolveLock::default(),
+            observers: SyncObservers::default(),
+            id,
+            operation,
+        })
+    }
+
+    /// Call `observer` whenever this node is recalculated and its
+    /// [NodeHash](crate::NodeHash) has changed.
+    ///
+    /// Observers are called on the resolving thread, once the value is no
+    /// longer being written to, but before any other thread can resolve this
+    /// node.
+    pub fn subscribe(&self, observer: impl FnMut(&NodeChange) + Send + 'static) {
+        self.observers.subscribe(observer);
+    }
+}
+
+impl<D, T, F> Resolve for SyncDerivedNode<D, T, F>
+where
+    for<'a> D: Resolve + IsDirtyInferenceWorkaround<'a> + 'a,
+    for<'a> T: UpdateDerived<<D as IsDirtyInferenceWorkaround<'a>>::OutputWorkaround, F>,
+    T: HashValue + Clean + Named,
+    F: Named,
+{
+    type Output<'a>
+        = RwLockReadGuard<'a, NodeState<T>>
+    where
+        Self: 'a;
+
+    fn resolve(&self, visitor: &mut impl Visitor) -> Result<Self::Output<'_>, ResolveError> {
+        trace_node(self, Some(self.operation.label()), || {
+            visitor.touch(self, Some(self.operation.label()));
+            let resolving = if visitor.visit(self) {
+                let resolving = self.resolving.acquire()?;
+                let mut node_state = self.value.write()?;
+                node_state.clean();
+                let input = self
+                    .dependencies
+                    .resolve_workaround(visitor)
+                    .inspect_err(|_| self.dependencies.rollback())?;
+                if input.is_dirty() {
+                    let previous = node_state.node_hash();
+                    node_state
+                        .value_mut()
+                        .update(input, &self.operation)
+                        .inspect_err(|_| self.dependencies.rollback())?;
+                    node_state.mark_changed();
+                    node_state.update_node_hash(&mut visitor.hasher());
+                    let current = node_state.node_hash();
+                    drop(node_state);
+                    visitor.notify_recalculated(self);
+                    self.observers.notify(self, previous, current);
+                }
+                Some(resolving)
+            } else {
+                // Reading the value would wait on a write lock which is never
+                // released, either by this thread or a branch waiting on it.
+                if let Some(rest) = visitor.take_cycle() {
+                    return Err(lock_cycle(Some(rest)));
+                }
+                self.resolving.check_reentry()?;
+                None
+            };
+            visitor.leave(self);
+            // The value is read before any other thread can resolve it again.
+            let value = self.value.read()?;
+            drop(resolving);
+            Ok(value)
+        })
+    }
+}
+
+impl<D, T: Named, F> Named for SyncDerivedNode<D, T, F> {
+    fn name() -> &'static str {
+        T::name()
+    }
+}
+
+impl<D, T: Named, F> Identifiable for SyncDerivedNode<D, T, F> {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::{
+        collections::HashSet,
+        ops::Deref,
+        rc::Rc,
+        sync::{Arc, Barrier, OnceLock},
+    };
+
+    use super::*;
+    use crate::{
+        error::{EarlyExit, NodeFrame},
+        execution::{
+            internal_test_utils::TestData, Dependency, DependencyEdge, DerivedNode,
+            DiagnosticVisitor, InputNode, NodeIds, ParallelVisitor, SyncDependencies2,
+            SyncDependency, SyncInputNode, SyncNodeRef,
+        },
+    };
+
+    struct Double;
+
+    impl Named for Double {
+        fn name() -> &'static str {
+            "Double"
+        }
+    }
+
+    // Shared by thread-safe and single-threaded nodes.
+    impl<R> UpdateDerived<DependencyEdge<'_, R>, Double> for TestData
+    where
+        R: Deref<Target = NodeState<TestData>>,
+    {
+        fn update(&mut self, deps: DependencyEdge<'_, R>, _: &Double) -> Result<(), EarlyExit> {
+            self.inner = deps.data().inner * 2;
+            Ok(())
+        }
+    }
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    #[test]
+    fn test_sync_derived_node() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = SyncInputNode::new(TestData::new(1));
+        let node = SyncDerivedNode::new(
+            SyncDependency::new(Arc::clone(&input)),
+            Double,
+            TestData::new(0),
+        );
+        assert_send_sync(&node);
+
+        // Every thread resolves the graph at once, waiting for each other.
+        let barrier = Arc::new(Barrier::new(4));
+        let handles = (0..4)
+            .map(|_| {
+                let node = Arc::clone(&node);
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    let mut visitor = DiagnosticVisitor::new();
+                    barrier.wait();
+                    assert_eq!(node.resolve(&mut visitor).unwrap().inner, 2);
+                })
+            })
+            .collect::<Vec<_>>();
+        handles.into_iter().for_each(|h| h.join().unwrap());
+
+        let mut visitor = DiagnosticVisitor::new();
+        input.update(5).unwrap();
+        assert_eq!(node.resolve(&mut visitor).unwrap().inner, 10);
+        assert_eq!(visitor.recalculated, HashSet::from([1]));
+        visitor.clear();
+        assert_eq!(node.resolve(&mut visitor).unwrap().inner, 10);
+        assert_eq!(visitor.recalculated, HashSet::new());
+
+        // The same operation can be used by a node which isn't thread-safe.
+        let input = InputNode::new(TestData::new(3));
+        let node = DerivedNode::new(Dependency::new(Rc::clone(&input)), Double, TestData::new(0));
+        assert_eq!(node.resolve_root(&mut visitor).unwrap().inner, 6);
+    }
+
+    type Node = SyncDerivedNode<SyncDependency<Arc<Late>>, TestData, Double>;
+
+    /// Resolves to a node which is only provided once the graph has been
+    /// built, making it possible to close a cycle.
+    struct Late {
+        node: OnceLock<Arc<Node>>,
+        /// If set, waited on before resolving the node.
+        meet: Option<Arc<Barrier>>,
+    }
+
+    impl Resolve for Late {
+        type Output<'a>
+            = SyncNodeRef<'a, TestData>
+        where
+            Self: 'a;
+
+        fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+            if let Some(meet) = &self.meet {
+                meet.wait();
+            }
+            self.node.get().unwrap().resolve(visitor)
+        }
+    }
+
+    /// A node which depends on whichever node is later set in the [Late].
+    fn late_node(meet: Option<Arc<Barrier>>) -> (Arc<Late>, Arc<Node>) {
+        let late = Arc::new(Late {
+            node: OnceLock::new(),
+            meet,
+        });
+        let node = SyncDerivedNode::new(
+            SyncDependency::new(Arc::clone(&late)),
+            Double,
+            TestData::new(0),
+        );
+        (late, node)
+    }
+
+    #[test]
+    fn test_sync_subscribe() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = SyncInputNode::new(TestData::new(1));
+        let node = SyncDerivedNode::new(
+            SyncDependency::new(Arc::clone(&input)),
+            Double,
+            TestData::new(0),
+        );
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let input_sender = sender.clone();
+        input.subscribe(move |change| input_sender.send((change.id, change.name)).unwrap());
+        node.subscribe(move |change| sender.send((change.id, change.name)).unwrap());
+
+        // The visitor is reused, so that values are hashed consistently.
+        let visitor = Arc::new(Mutex::new(DiagnosticVisitor::new()));
+        let resolve = || {
+            let node = Arc::clone(&node);
+            let visitor = Arc::clone(&visitor);
+            std::thread::spawn(move || {
+                let mut visitor = visitor.lock().unwrap();
+                visitor.clear();
+                node.resolve(&mut *visitor).unwrap().inner
+            })
+            .join()
+            .unwrap()
+        };
+        // Observers are called on the resolving thread.
+        assert_eq!(resolve(), 2);
+        let changes = receiver.try_iter().collect::<Vec<_>>();
+        assert_eq!(changes, vec![(0, "TestData"), (1, "TestData")]);
+
+        assert_eq!(resolve(), 2);
+        assert_eq!(receiver.try_iter().count(), 0);
+
+        input.update(2).unwrap();
+        assert_eq!(resolve(), 4);
+        let changes = receiver.try_iter().collect::<Vec<_>>();
+        assert_eq!(changes, vec![(0, "TestData"), (1, "TestData")]);
+    }
+
+    #[test]
+    fn test_sync_cycle() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let (late, node) = late_node(None);
+        late.node.set(Arc::clone(&node)).ok().unwrap();
+
+        // Re-entering the node is detected rather than waiting on itself.
+        let err = node
+            .resolve_root(&mut DiagnosticVisitor::new())
+            .unwrap_err();
+        assert!(matches!(err, ResolveError::Cycle(_)));
+        let frame = NodeFrame::new(node.as_ref(), Some("Double"));
+        assert_eq!(err.cycle(), Some([frame, frame].as_slice()));
+        assert_eq!(
+            err.to_string(),
+            "cycle detected: lock would block at TestData#0 (Double) -> TestData#0 (Double)"
+        );
+        // The same is true when the node is reached by a parallel branch.
+        let err = node
+            .resolve_root(&mut ParallelVisitor::with_threads(1))
+            .unwrap_err();
+        assert_eq!(err.cycle(), Some([frame, frame].as_slice()));
+    }
+
+    #[test]
+    fn test_sync_cycle_between_branches() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        // Each branch claims its node before either reaches the other.
+        let meet = Arc::new(Barrier::new(2));
+        let (late_a, a) = late_node(Some(Arc::clone(&meet)));
+        let (late_b, b) = late_node(Some(meet));
+        late_a.node.set(Arc::clone(&b)).ok().unwrap();
+        late_b.node.set(Arc::clone(&a)).ok().unwrap();
+        let frames = [
+            NodeFrame::new(a.as_ref(), Some("Double")),
+            NodeFrame::new(b.as_ref(), Some("Double")),
+        ];
+
+        // `a` and `b` are each resolved by their own branch, which then waits
+        // on the other. The cycle is reported rather than waiting forever.
+        let group = SyncDependencies2::new(Arc::clone(&a), Arc::clone(&b));
+        let mut visitor = ParallelVisitor::with_threads(1);
+        for _ in 0..20 {
+            let Err(err) = group.resolve_root(&mut visitor) else {
+                panic!("expected a cycle");
+            };
+            assert!(matches!(err, ResolveError::Cycle(_)));
+            let cycle = err.cycle().unwrap();
+            assert_eq!(cycle.len(), 3);
+            assert_eq!(cycle.first(), cycle.last());
+            assert!(cycle.iter().all(|frame| frames.contains(frame)));
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::rc::Rc;
+
+use crate::execution::{
+    error::ResolveResult, visitor::DynVisitor, Identifiable, Named, NodeRef, Resolve, Staleness,
+    Visitor,
+};
+
+/// An object-safe version of [Resolve], for nodes which resolve to a
+/// [NodeRef].
+trait ResolveObject<T> {
+    fn dyn_id(&self) -> usize;
+
+    fn dyn_label(&self) -> &'static str;
+
+    fn dyn_resolve(&self, visitor: &mut DynVisitor<'_>) -> ResolveResult<NodeRef<'_, T>>;
+
+    fn dyn_subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool;
+}
+
+impl<N, T> ResolveObject<T> for N
+where
+    N: Identifiable + for<'a> Resolve<Output<'a> = NodeRef<'a, T>> + 'static,
+{
+    fn dyn_id(&self) -> usize {
+        self.id()
+    }
+
+    fn dyn_label(&self) -> &'static str {
+        self.label()
+    }
+
+    fn dyn_resolve(&self, visitor: &mut DynVisitor<'_>) -> ResolveResult<NodeRef<'_, T>> {
+        self.resolve(visitor)
+    }
+
+    fn dyn_subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        self.subscribe_stale(dependee)
+    }
+}
+
+/// # Dyn Node
+///
+/// Any node which resolves to a [NodeRef] of `T`, with its type (and that of
+/// its dependencies) erased. This allows graphs to be assembled at runtime,
+/// for example from configuration, where the shape of the graph can't be
+/// known at compile time.
+///
+/// A `DynNode` can be used as a dependency anywhere a concrete node can.
+///
+/// > The type of each node beneath a `DynNode` is erased, so it's passed to
+/// > the [Visitor] as a stand-in carrying its Id and its
+/// > [label](Named::label). Visitors which render the name of each node
+/// > should use the label, as [Named::name] is that of the stand-in.
+///
+/// ```
+/// # use std::{collections::HashMap, rc::Rc};
+/// # use depends::{DepRefVec, DependencyVec, DerivedNode, DynNode, HashSetVisitor, InputNode, Resolve, UpdateDerived};
+/// # use depends::derives::Operation;
+/// # use depends::error::EarlyExit;
+/// # #[derive(Operation)]
+/// # struct Sum;
+/// # impl UpdateDerived<DepRefVec<'_, i64>, Sum> for i64 {
+/// #     fn update(&mut self, deps: DepRefVec<'_, i64>, _: &Sum) -> Result<(), EarlyExit> {
+/// #         *self = deps.iter().map(|dep| *dep.data().value()).sum();
+/// #         Ok(())
+/// #     }
+/// # }
+/// let mut registry = HashMap::new();
+/// registry.insert("a", DynNode::new(InputNode::new(1_i64)));
+/// registry.insert("b", DynNode::new(InputNode::new(2_i64)));
+///
+/// // Nodes of different types can be stored, and depended upon, together.
+/// let sum = DerivedNode::new(
+///     DependencyVec::new([registry["a"].clone(), registry["b"].clone()]),
+///     Sum,
+///     0_i64,
+/// );
+/// registry.insert("sum", DynNode::new(sum));
+///
+/// let mut visitor = HashSetVisitor::new();
+/// assert_eq!(*registry["sum"].resolve_root(&mut visitor).unwrap().value(), 3);
+/// ```
+pub struct DynNode<T>(Rc<dyn ResolveObject<T>>);
+
+impl<T> DynNode<T> {
+    /// Erase the type of `node`.
+    pub fn new<N>(node: Rc<N>) -> Self
+    where
+        N: Identifiable + for<'a> Resolve<Output<'a> = NodeRef<'a, T>> + 'static,
+    {
+        Self(node)
+    }
+}
+
+impl<T> Clone for DynNode<T> {
+    fn clone(&self) -> Self {
+        Self(Rc::clone(&self.0))
+    }
+}
+
+impl<T> Resolve for DynNode<T> {
+    type Output<'a>
+        = NodeRef<'a, T>
+    where
+        Self: 'a;
+
+    fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        self.0.dyn_resolve(&mut DynVisitor::new(visitor))
+    }
+
+    fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        self.0.dyn_subscribe_stale(dependee)
+    }
+}
+
+impl<T: Named> Named for DynNode<T> {
+    fn name() -> &'static str {
+        T::name()
+    }
+
+    fn label(&self) -> &'static str {
+        self.0.dyn_label()
+    }
+}
+
+impl<T: Named> Identifiable for DynNode<T> {
+    fn id(&self) -> usize {
+        self.0.dyn_id()
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::{collections::HashSet, hash::DefaultHasher};
+
+    use super::*;
+    use crate::{
+        error::EarlyExit,
+        execution::{internal_test_utils::TestData, DiagnosticVisitor, NodeIds},
+        DepRef, DepRefVec, Dependency, DependencyVec, DerivedNode, InputNode, UpdateDerived,
+    };
+
+    struct Sum;
+
+    impl Named for Sum {
+        fn name() -> &'static str {
+            "Sum"
+        }
+    }
+
+    impl UpdateDerived<DepRefVec<'_, TestData>, Sum> for TestData {
+        fn update(&mut self, deps: DepRefVec<'_, TestData>, _: &Sum) -> Result<(), EarlyExit> {
+            self.inner = deps.iter().map(|dep| dep.data().inner).sum();
+            Ok(())
+        }
+    }
+
+    struct Double;
+
+    impl Named for Double {
+        fn name() -> &'static str {
+            "Double"
+        }
+    }
+
+    impl UpdateDerived<DepRef<'_, TestData>, Double> for TestData {
+        fn update(&mut self, deps: DepRef<'_, TestData>, _: &Double) -> Result<(), EarlyExit> {
+            self.inner = deps.data().inner * 2;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_dyn_node() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let a = InputNode::new(TestData::new(1));
+        let b = InputNode::new(TestData::new(2));
+        let inputs = [DynNode::new(Rc::clone(&a)), DynNode::new(Rc::clone(&b))];
+        assert_eq!(
+            inputs.iter().map(Identifiable::id).collect::<Vec<_>>(),
+            [0, 1]
+        );
+        let sum = DynNode::new(DerivedNode::new(
+            DependencyVec::new(inputs.iter().cloned()),
+            Sum,
+            TestData::new(0),
+        ));
+        let double = DerivedNode::new(Dependency::new(sum.clone()), Double, TestData::new(0));
+        let mut visitor = DiagnosticVisitor::new();
+
+        assert_eq!(double.resolve(&mut visitor).unwrap().inner, 6);
+        assert_eq!(visitor.recalculated, [2, 3].into());
+        visitor.clear();
+
+        // Cached values are reused through the erased node.
+        assert_eq!(double.resolve(&mut visitor).unwrap().inner, 6);
+        assert!(visitor.recalculated.is_empty());
+        visitor.clear();
+
+        b.update(5).unwrap();
+        assert_eq!(double.resolve(&mut visitor).unwrap().inner, 12);
+        assert_eq!(visitor.recalculated, [2, 3].into());
+    }
+
+    /// Records the label and operation of each node touched.
+    #[derive(Default)]
+    struct Touched {
+        visited: HashSet<usize>,
+        touched: Vec<(&'static str, Option<&'static str>)>,
+    }
+
+    impl Visitor for Touched {
+        type Hasher = DefaultHasher;
+
+        fn visit<N: Identifiable>(&mut self, node: &N) -> bool {
+            self.visited.insert(node.id())
+        }
+
+        fn clear(&mut self) {
+            self.visited.clear();
+        }
+
+        fn touch<N: Identifiable>(&mut self, node: &N, operation: Option<&'static str>) {
+            self.touched.push((node.label(), operation));
+        }
+
+        fn hasher(&self) -> Self::Hasher {
+            DefaultHasher::new()
+        }
+    }
+
+    #[test]
+    fn test_dyn_node_labels() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = DynNode::new(InputNode::new(TestData::new(1)));
+        let double = DynNode::new(DerivedNode::new(
+            Dependency::new(input),
+            Double,
+            TestData::new(0),
+        ));
+        let mut visitor = Touched::default();
+
+        assert_eq!(double.resolve(&mut visitor).unwrap().inner, 2);
+        // Visitors see the labels of the erased nodes, not the stand-in.
+        assert_eq!(
+            visitor.touched,
+            [("TestData", Some("Double")), ("TestData", None)]
+        );
+    }
+}
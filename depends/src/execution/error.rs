@@ -1,46 +1,277 @@
 use std::{
     borrow::Cow,
-    cell::{BorrowError, BorrowMutError},
+    cell::{BorrowError, BorrowMutError, Ref, RefCell, RefMut},
+    error::Error,
+    fmt,
+    ops::Deref,
+    sync::{PoisonError, TryLockError},
 };
 
 use thiserror::Error;
 
+use super::Identifiable;
+
 pub type ResolveResult<T> = Result<T, ResolveError>;
 
 /// Any error that can occur when resolving a node.
 ///
-/// We may be able to introduce a generic type parameter `<E>` for the custom
-/// error, but this would require some invasive changes to the `Resolve`
-/// trait to maintain flexibility. For instance, we'd want to assert that
-/// a caller may only `resolve<E>` a node if it provides an `E` where
-/// _all_ inner nodes can be `Into<E>`. This is a non-trivial constraint (if
-/// it's even possible).
+/// A generic type parameter `<E>` for the custom error would require every
+/// node in a graph to agree on (or convert in to) a single error type, which
+/// is a non-trivial constraint on the `Resolve` trait. Instead, custom errors
+/// are boxed inside [EarlyExit] and can be recovered with
+/// [downcast_ref](EarlyExit::downcast_ref).
+///
+/// Each error is [Traced] with the node which raised it, and the path to that
+/// node from the node being resolved.
 #[derive(Debug, Error)]
 pub enum ResolveError {
-    /// Either a borrow or borrow_mut error occurred when resolving a node.
-    /// This either means there's a cyclic dependency or a read-reference to
-    /// a previous result is being held.
+    /// Either a borrow or borrow_mut error occurred when resolving a node, or
+    /// a lock on a thread-safe node was poisoned or already held by the
+    /// current thread. Failures which
+    /// can be attributed to a [Cycle](Self::Cycle) or an
+    /// [OutstandingBorrow](Self::OutstandingBorrow) are reported as such.
     #[error("{0}")]
-    BorrowError(#[from] AnyBorrowError),
-    /// A custom Error. This is just a string detailing the error. Use this
-    /// if you want a node to abort a resolution early.
+    BorrowError(Traced<AnyBorrowError>),
+    /// A node was reached again whilst it was still being resolved. The
+    /// nodes which form the cycle are available from
+    /// [cycle](ResolveError::cycle).
+    #[error("cycle detected: {0}")]
+    Cycle(Traced<AnyBorrowError>),
+    /// A node could not be updated as a read-reference to its value (such as
+    /// a [NodeRef](crate::NodeRef)) is still being held. The node is
+    /// available from [node](ResolveError::node).
+    #[error("outstanding borrow: {0}")]
+    OutstandingBorrow(Traced<AnyBorrowError>),
+    /// A custom Error, raised by an operation. Use this if you want a node to
+    /// abort a resolution early.
     #[error("early exit: {0}")]
-    EarlyExit(#[from] EarlyExit),
+    EarlyExit(Traced<EarlyExit>),
+    /// An update was rejected by
+    /// [TryUpdateInput::validate_update](crate::TryUpdateInput::validate_update),
+    /// or a batch by a [Transaction](crate::Transaction) hook. The error can
+    /// be recovered with [downcast_ref](EarlyExit::downcast_ref).
+    #[error("rejected update: {0}")]
+    Rejected(Traced<EarlyExit>),
+}
+
+impl ResolveError {
+    /// The path from the node being resolved to the node which raised this
+    /// error.
+    pub fn trace(&self) -> &[NodeFrame] {
+        match self {
+            Self::BorrowError(e) | Self::Cycle(e) | Self::OutstandingBorrow(e) => e.trace(),
+            Self::EarlyExit(e) | Self::Rejected(e) => e.trace(),
+        }
+    }
+
+    /// If this is a [Cycle](Self::Cycle), the path from the node which was
+    /// re-entered back around to itself.
+    pub fn cycle(&self) -> Option<&[NodeFrame]> {
+        let Self::Cycle(e) = self else {
+            return None;
+        };
+        let node = e.node()?;
+        let start = e.trace().iter().position(|frame| frame.id == node.id)?;
+        Some(&e.trace()[start..])
+    }
+
+    /// The node which raised this error, if known.
+    pub fn node(&self) -> Option<&NodeFrame> {
+        self.trace().last()
+    }
+
+    /// Record that this error passed through `node` whilst being returned.
+    pub fn with_node<N: Identifiable>(mut self, node: &N, operation: Option<&'static str>) -> Self {
+        let frame = NodeFrame::new(node, operation);
+        match &mut self {
+            Self::BorrowError(e) | Self::Cycle(e) | Self::OutstandingBorrow(e) => {
+                e.push_front(frame)
+            }
+            Self::EarlyExit(e) | Self::Rejected(e) => e.push_front(frame),
+        }
+        self
+    }
+}
+
+/// Mutably borrow the state of a node. If this fails, the node is either
+/// still being resolved further up the stack, or a read-reference to it is
+/// being held elsewhere.
+pub(crate) fn borrow_state_mut<T>(state: &RefCell<T>) -> ResolveResult<RefMut<'_, T>> {
+    state.try_borrow_mut().map_err(|e| {
+        let err = Traced::new(AnyBorrowError::from(e));
+        if state.try_borrow().is_ok() {
+            ResolveError::OutstandingBorrow(err)
+        } else {
+            ResolveError::Cycle(err)
+        }
+    })
+}
+
+/// Borrow the state of a node. This can only fail if the node is still being
+/// resolved further up the stack.
+pub(crate) fn borrow_state<T>(state: &RefCell<T>) -> ResolveResult<Ref<'_, T>> {
+    state
+        .try_borrow()
+        .map_err(|e| ResolveError::Cycle(Traced::new(e.into())))
+}
+
+/// A node was reached again whilst still being resolved, and waiting for it
+/// would never complete. `rest` is the remainder of the cycle taken from the
+/// [Visitor](super::Visitor), if it tracks it.
+pub(crate) fn lock_cycle(rest: Option<Vec<NodeFrame>>) -> ResolveError {
+    ResolveError::Cycle(Traced {
+        error: AnyBorrowError::WouldBlock,
+        trace: rest.unwrap_or_default(),
+    })
+}
+
+/// Run `f`, recording `node` in the trace of any error it returns.
+pub(crate) fn trace_node<N: Identifiable, T>(
+    node: &N,
+    operation: Option<&'static str>,
+    f: impl FnOnce() -> ResolveResult<T>,
+) -> ResolveResult<T> {
+    f().map_err(|e| e.with_node(node, operation))
+}
+
+impl From<AnyBorrowError> for ResolveError {
+    fn from(err: AnyBorrowError) -> Self {
+        Self::BorrowError(Traced::new(err))
+    }
+}
+
+impl From<EarlyExit> for ResolveError {
+    fn from(err: EarlyExit) -> Self {
+        Self::EarlyExit(Traced::new(err))
+    }
 }
 
 impl From<BorrowError> for ResolveError {
     fn from(err: BorrowError) -> Self {
-        Self::BorrowError(AnyBorrowError::BorrowError(err))
+        AnyBorrowError::BorrowError(err).into()
     }
 }
 
 impl From<BorrowMutError> for ResolveError {
     fn from(err: BorrowMutError) -> Self {
-        Self::BorrowError(AnyBorrowError::BorrowMutError(err))
+        AnyBorrowError::BorrowMutError(err).into()
+    }
+}
+
+impl<T> From<TryLockError<T>> for ResolveError {
+    fn from(err: TryLockError<T>) -> Self {
+        AnyBorrowError::from(err).into()
+    }
+}
+
+impl<T> From<PoisonError<T>> for ResolveError {
+    fn from(_: PoisonError<T>) -> Self {
+        AnyBorrowError::Poisoned.into()
+    }
+}
+
+/// A node through which a [ResolveError] was returned.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NodeFrame {
+    /// The node's [Identifiable::id].
+    pub id: usize,
+    /// The node's [Named::name](super::Named::name).
+    pub name: &'static str,
+    /// The name of the node's operation, if it's a derived node.
+    pub operation: Option<&'static str>,
+}
+
+impl NodeFrame {
+    pub fn new<N: Identifiable>(node: &N, operation: Option<&'static str>) -> Self {
+        Self {
+            id: node.id(),
+            name: node.label(),
+            operation,
+        }
+    }
+}
+
+impl fmt::Display for NodeFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.operation {
+            Some(op) => write!(f, "{}#{} ({op})", self.name, self.id),
+            None => write!(f, "{}#{}", self.name, self.id),
+        }
+    }
+}
+
+/// An error, along with the path from the node being resolved to the node
+/// which raised it.
+#[derive(Debug)]
+pub struct Traced<E> {
+    error: E,
+    /// Ordered from the node being resolved to the node which raised the
+    /// error.
+    trace: Vec<NodeFrame>,
+}
+
+impl<E> Traced<E> {
+    pub fn new(error: E) -> Self {
+        Self {
+            error,
+            trace: Vec::new(),
+        }
+    }
+
+    /// The path from the node being resolved to the node which raised this
+    /// error.
+    pub fn trace(&self) -> &[NodeFrame] {
+        &self.trace
+    }
+
+    /// The node which raised this error, if known.
+    pub fn node(&self) -> Option<&NodeFrame> {
+        self.trace.last()
+    }
+
+    /// Take the inner error, discarding the trace.
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+
+    fn push_front(&mut self, frame: NodeFrame) {
+        // Errors are rare and graphs shallow, so this is cheap enough.
+        self.trace.insert(0, frame);
     }
 }
 
-/// Any error that can occur when borrowing a [RefCell](std::cell::RefCell)
+impl<E> Deref for Traced<E> {
+    type Target = E;
+
+    fn deref(&self) -> &Self::Target {
+        &self.error
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for Traced<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)?;
+        if !self.trace.is_empty() {
+            f.write_str(" at ")?;
+            for (i, frame) in self.trace.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(" -> ")?;
+                }
+                frame.fmt(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<E: Error + 'static> Error for Traced<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Any error that can occur when borrowing a [RefCell](std::cell::RefCell),
+/// or locking an [RwLock](std::sync::RwLock) or [Mutex](std::sync::Mutex),
 /// inside a node.
 #[derive(Debug, Error)]
 pub enum AnyBorrowError {
@@ -50,34 +281,114 @@ pub enum AnyBorrowError {
     /// Tried to borrow mutably while a read-reference was held.
     #[error("borrow mut error")]
     BorrowMutError(#[from] BorrowMutError),
+    /// Tried to acquire a lock which was already held elsewhere, or which
+    /// was held by the current thread.
+    #[error("lock would block")]
+    WouldBlock,
+    /// Tried to acquire a lock which was poisoned by a panicking thread.
+    #[error("lock poisoned")]
+    Poisoned,
+}
+
+impl<T> From<TryLockError<T>> for AnyBorrowError {
+    fn from(err: TryLockError<T>) -> Self {
+        match err {
+            TryLockError::WouldBlock => Self::WouldBlock,
+            TryLockError::Poisoned(_) => Self::Poisoned,
+        }
+    }
 }
 
 /// Abort the resolution of a graph immediately and return this custom error.
 ///
-/// We might be able to make this a generic type eventually, but for now it's
-/// just a string describing the error.
+/// This is either a string describing the error, or any custom error type,
+/// which callers can recover with [downcast_ref](Self::downcast_ref).
+///
+/// ```
+/// # use depends::error::EarlyExit;
+/// #[derive(Debug, PartialEq)]
+/// struct LimitExceeded(usize);
+///
+/// impl std::fmt::Display for LimitExceeded {
+///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+///         write!(f, "limit exceeded ({})", self.0)
+///     }
+/// }
+///
+/// impl std::error::Error for LimitExceeded {}
+///
+/// let err = EarlyExit::custom(LimitExceeded(5));
+/// assert_eq!(err.to_string(), "limit exceeded (5)");
+/// assert_eq!(err.downcast_ref::<LimitExceeded>(), Some(&LimitExceeded(5)));
+///
+/// let err = EarlyExit::new("something went wrong");
+/// assert_eq!(err.downcast_ref::<LimitExceeded>(), None);
+/// ```
 #[derive(Debug, Error)]
-#[error("{0}")]
-pub struct EarlyExit(Cow<'static, str>);
+#[error(transparent)]
+pub struct EarlyExit(Box<dyn Error + Send + Sync + 'static>);
 
 impl EarlyExit {
+    /// An error which is just a string describing the error.
     pub fn new<S: Into<Cow<'static, str>>>(err: S) -> Self {
-        Self(err.into())
+        Self(Box::new(Message(err.into())))
+    }
+
+    /// An error of any custom type.
+    pub fn custom<E: Error + Send + Sync + 'static>(err: E) -> Self {
+        Self(Box::new(err))
+    }
+
+    /// Whether the inner error is of type `E`.
+    pub fn is<E: Error + 'static>(&self) -> bool {
+        self.0.is::<E>()
+    }
+
+    /// A reference to the inner error, if it is of type `E`.
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&E> {
+        self.0.downcast_ref()
+    }
+
+    /// Take the inner error, if it is of type `E`. Otherwise, return `self`.
+    pub fn downcast<E: Error + 'static>(self) -> Result<E, Self> {
+        self.0.downcast().map(|e| *e).map_err(Self)
+    }
+
+    /// The inner error.
+    pub fn into_inner(self) -> Box<dyn Error + Send + Sync + 'static> {
+        self.0
     }
 }
 
 impl From<String> for EarlyExit {
     fn from(err: String) -> Self {
-        Self(Cow::Owned(err))
+        Self::new(err)
     }
 }
 
 impl From<&'static str> for EarlyExit {
     fn from(err: &'static str) -> Self {
-        Self(Cow::Borrowed(err))
+        Self::new(err)
+    }
+}
+
+/// A string describing an [EarlyExit].
+struct Message(Cow<'static, str>);
+
+impl fmt::Debug for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
     }
 }
 
+impl Error for Message {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,7 +410,7 @@ mod tests {
                 assert!(matches!(err, ResolveError::BorrowError(_)));
                 assert_eq!(
                     format!("{err:?}"),
-                    "BorrowError(BorrowMutError(BorrowMutError))"
+                    "BorrowError(Traced { error: BorrowMutError(BorrowMutError), trace: [] })"
                 );
                 assert_eq!(format!("{err}"), "borrow mut error");
             } else {
@@ -110,7 +421,7 @@ mod tests {
                 assert!(matches!(err, ResolveError::BorrowError(_)));
                 assert_eq!(
                     format!("{err:?}"),
-                    "BorrowError(BorrowMutError(BorrowMutError))"
+                    "BorrowError(Traced { error: BorrowMutError(BorrowMutError), trace: [] })"
                 );
                 assert_eq!(format!("{err}"), "borrow mut error");
             } else {
@@ -123,7 +434,10 @@ mod tests {
                 let err: AnyBorrowError = e.into();
                 let err: ResolveError = err.into();
                 assert!(matches!(err, ResolveError::BorrowError(_)));
-                assert_eq!(format!("{err:?}"), "BorrowError(BorrowError(BorrowError))");
+                assert_eq!(
+                    format!("{err:?}"),
+                    "BorrowError(Traced { error: BorrowError(BorrowError), trace: [] })"
+                );
                 assert_eq!(format!("{err}"), "borrow error");
             } else {
                 panic!("expected borrow error");
@@ -131,7 +445,10 @@ mod tests {
             if let Err(e) = refcell.try_borrow() {
                 let err: ResolveError = e.into();
                 assert!(matches!(err, ResolveError::BorrowError(_)));
-                assert_eq!(format!("{err:?}"), "BorrowError(BorrowError(BorrowError))");
+                assert_eq!(
+                    format!("{err:?}"),
+                    "BorrowError(Traced { error: BorrowError(BorrowError), trace: [] })"
+                );
                 assert_eq!(format!("{err}"), "borrow error");
             } else {
                 panic!("expected borrow error");
@@ -140,7 +457,10 @@ mod tests {
         let err = EarlyExit::new("test");
         let err: ResolveError = err.into();
         assert!(matches!(err, ResolveError::EarlyExit(_)));
-        assert_eq!(format!("{err:?}"), r#"EarlyExit(EarlyExit("test"))"#);
+        assert_eq!(
+            format!("{err:?}"),
+            r#"EarlyExit(Traced { error: EarlyExit("test"), trace: [] })"#
+        );
         assert_eq!(format!("{err}"), "early exit: test");
     }
     #[test]
@@ -166,15 +486,81 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_lock_error() {
+        let lock = std::sync::RwLock::new(());
+        {
+            let _a = lock.read().unwrap();
+            if let Err(e) = lock.try_write() {
+                let err: ResolveError = e.into();
+                assert!(matches!(
+                    err,
+                    ResolveError::BorrowError(ref e) if matches!(**e, AnyBorrowError::WouldBlock)
+                ));
+                assert_eq!(
+                    format!("{err:?}"),
+                    "BorrowError(Traced { error: WouldBlock, trace: [] })"
+                );
+                assert_eq!(format!("{err}"), "lock would block");
+            } else {
+                panic!("expected lock error");
+            };
+        }
+        let lock = std::sync::Arc::new(std::sync::Mutex::new(()));
+        let poisoned = std::sync::Arc::clone(&lock);
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoned.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+        if let Err(e) = lock.try_lock() {
+            let err: ResolveError = e.into();
+            assert!(matches!(
+                err,
+                ResolveError::BorrowError(ref e) if matches!(**e, AnyBorrowError::Poisoned)
+            ));
+            assert_eq!(format!("{err}"), "lock poisoned");
+        } else {
+            panic!("expected lock error");
+        };
+    }
+
     #[test]
     fn test_early_exit() {
         let err = EarlyExit::new("test");
-        assert_eq!(err.0, Cow::Borrowed("test"));
+        assert_eq!(format!("{err}"), "test");
         let err = EarlyExit::from("exit");
-        assert_eq!(err.0, Cow::Borrowed("exit"));
+        assert_eq!(format!("{err}"), "exit");
         let err = EarlyExit::from(String::from("see ya"));
-        assert_eq!(err.0, Cow::<'static, str>::Owned(String::from("see ya")));
         assert_eq!(format!("{err}"), "see ya");
         assert_eq!(format!("{err:?}"), r#"EarlyExit("see ya")"#);
+        assert!(err.is::<Message>());
+        assert!(!err.is::<std::fmt::Error>());
+    }
+
+    #[test]
+    fn test_early_exit_custom() {
+        let err: ResolveError = EarlyExit::custom(std::fmt::Error).into();
+        assert_eq!(
+            format!("{err}"),
+            "early exit: an error occurred when formatting an argument"
+        );
+        assert_eq!(
+            format!("{err:?}"),
+            "EarlyExit(Traced { error: EarlyExit(Error), trace: [] })"
+        );
+        let ResolveError::EarlyExit(err) = err else {
+            panic!("expected early exit");
+        };
+        assert!(err.is::<std::fmt::Error>());
+        assert_eq!(
+            err.downcast_ref::<std::fmt::Error>(),
+            Some(&std::fmt::Error)
+        );
+        assert!(err.downcast_ref::<Message>().is_none());
+        let err = err.into_inner().downcast::<Message>().unwrap_err();
+        assert_eq!(err.downcast::<std::fmt::Error>().unwrap(), std::fmt::Error);
+        let inner = EarlyExit::new("boxed").into_inner();
+        assert_eq!(inner.to_string(), "boxed");
     }
 }
@@ -0,0 +1,47 @@
+use crate::execution::{error::EarlyExit, Clean, HashValue, Named, UpdateDerived};
+
+/// An operation defined by a closure, rather than an [UpdateDerived]
+/// implementation. The closure is labelled by a string, which is given as
+/// its [Named::label] to visitors and graphviz output.
+///
+/// Usually constructed with
+/// [DerivedNode::from_fn](crate::DerivedNode::from_fn).
+pub struct FnOperation<C> {
+    label: &'static str,
+    f: C,
+}
+
+impl<C> FnOperation<C> {
+    pub fn new(label: &'static str, f: C) -> Self {
+        Self { label, f }
+    }
+}
+
+impl<C> Named for FnOperation<C> {
+    fn name() -> &'static str {
+        "FnOperation"
+    }
+
+    fn label(&self) -> &'static str {
+        self.label
+    }
+}
+
+/// Marks a node value which can be updated by an [FnOperation].
+///
+/// This is implemented by `#[derive(Value)]`, and for primitives. Values
+/// implementing [HashValue] and [Clean] by hand can opt in with an empty
+/// implementation.
+pub trait UpdateFn: HashValue + Clean {}
+
+// Only node values are bound, so the `update` method doesn't shadow that of
+// the nodes themselves, nor overlap with that of `NodeState`.
+impl<T, D, C> UpdateDerived<D, FnOperation<C>> for T
+where
+    T: UpdateFn,
+    C: Fn(&mut T, D) -> Result<(), EarlyExit>,
+{
+    fn update(&mut self, deps: D, operation: &FnOperation<C>) -> Result<(), EarlyExit> {
+        (operation.f)(self, deps)
+    }
+}
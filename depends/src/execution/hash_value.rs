@@ -1,10 +1,16 @@
 use std::{hash::Hasher, ops::Deref};
 
 use super::NodeHash;
-use crate::NodeRef;
+use crate::{NodeRef, SyncNodeRef};
 
 /// A unique number derived from the internal state of a node.
 pub trait HashValue {
+    /// Whether this value is versioned, rather than hashed. The
+    /// [NodeHash] of a versioned value is a counter which is incremented each
+    /// time the value is updated, and [hash_value](Self::hash_value) is never
+    /// called.
+    const VERSIONED: bool = false;
+
     /// Either a unique number, or a value detailing that this node cannot be
     /// hashed.
     fn hash_value(&self, hasher: &mut impl Hasher) -> NodeHash;
@@ -15,3 +21,9 @@ impl<T: HashValue> HashValue for NodeRef<'_, T> {
         self.deref().hash_value(hasher)
     }
 }
+
+impl<T: HashValue> HashValue for SyncNodeRef<'_, T> {
+    fn hash_value(&self, hasher: &mut impl Hasher) -> NodeHash {
+        self.deref().hash_value(hasher)
+    }
+}
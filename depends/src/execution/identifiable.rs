@@ -1,26 +1,135 @@
 use std::{
+    cell::RefCell,
+    marker::PhantomData,
     rc::Rc,
-    sync::atomic::{AtomicUsize, Ordering},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 use super::Named;
 
-/// Global node Ids are kept in order to track execution across graphs.
+/// Ids of nodes created outside of any [NodeIds] scope are unique across the
+/// process, in order to track execution across graphs.
 static NODE_ID: AtomicUsize = AtomicUsize::new(0);
 
-/// Public interface to the global [NODE_ID].
+thread_local! {
+    /// The counter of each [NodeIds] scope entered on this thread, innermost
+    /// last.
+    static SCOPES: RefCell<Vec<Arc<AtomicUsize>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The Id of the next node to be created, taken from the innermost [NodeIds]
+/// scope entered on this thread, or the process-global counter otherwise.
 #[doc(hidden)]
 pub fn next_node_id() -> usize {
-    NODE_ID.fetch_add(1, Ordering::Relaxed)
+    SCOPES.with(|scopes| {
+        match scopes.borrow().last() {
+            Some(next) => next.fetch_add(1, Ordering::Relaxed),
+            None => NODE_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    })
 }
 
-#[cfg(test)]
-pub fn reset_node_id() {
-    NODE_ID.store(0, Ordering::Relaxed);
+/// # Node Ids
+///
+/// Allocates the Ids of the nodes of a single graph. Nodes created whilst a
+/// `NodeIds` scope is entered take dense Ids starting at zero, regardless of
+/// any other graphs created before or alongside them. This keeps Ids (and
+/// anything rendered from them, such as Graphviz output) deterministic.
+///
+/// A scope can be entered again later to add nodes to the same graph, and its
+/// Ids carry on from where they left off.
+///
+/// A scope only applies to the thread which entered it. To create nodes of
+/// the same graph on other threads, each thread must enter the scope itself,
+/// in which case the order of their Ids depends on the order the nodes were
+/// created in.
+///
+/// > Ids are only unique within a scope. Nodes created outside of any scope
+/// > share a separate, process-global counter, which also starts at zero. Nodes
+/// > from different scopes, or from a scope and outside of any, must not be
+/// > resolved with the same [Visitor](super::Visitor).
+///
+/// ```
+/// # use depends::{Identifiable, InputNode, NodeIds};
+/// let ids = NodeIds::new();
+/// let (a, b) = ids.scope(|| (InputNode::new(1_i32), InputNode::new(2_i32)));
+/// assert_eq!((a.id(), b.id()), (0, 1));
+///
+/// // Another graph gets its own Ids.
+/// let c = NodeIds::new().scope(|| InputNode::new(3_i32));
+/// assert_eq!(c.id(), 0);
+///
+/// // Ids of the first graph carry on from where they left off, including
+/// // on other threads.
+/// let d = std::thread::scope(|s| s.spawn(|| ids.scope(|| InputNode::new(4_i32).id())).join());
+/// assert_eq!(d.unwrap(), 2);
+/// ```
+#[derive(Debug, Default)]
+pub struct NodeIds {
+    /// The Id of the next node created in this scope.
+    next: Arc<AtomicUsize>,
+}
+
+impl NodeIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create the nodes of this graph. Any nodes created by `f` on this thread
+    /// take their Ids from this scope.
+    pub fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _guard = self.enter();
+        f()
+    }
+
+    /// Enter this scope on this thread until the returned guard is dropped.
+    /// Guards must be dropped in the reverse order they were created.
+    pub fn enter(&self) -> NodeIdsGuard<'_> {
+        SCOPES.with(|scopes| scopes.borrow_mut().push(Arc::clone(&self.next)));
+        NodeIdsGuard {
+            ids: self,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Leaves a [NodeIds] scope when dropped.
+#[must_use = "the scope is left as soon as the guard is dropped"]
+pub struct NodeIdsGuard<'a> {
+    ids: &'a NodeIds,
+    /// The scope is entered on a single thread, so must be left on it too.
+    phantom: PhantomData<*const ()>,
+}
+
+impl Drop for NodeIdsGuard<'_> {
+    fn drop(&mut self) {
+        SCOPES.with(|scopes| {
+            let mut scopes = scopes.borrow_mut();
+            // A guard dropped out of order only leaves its own scope, so the
+            // scopes entered after it are still current.
+            let Some(index) = scopes
+                .iter()
+                .rposition(|next| Arc::ptr_eq(next, &self.ids.next))
+            else {
+                return;
+            };
+            let innermost = index + 1 == scopes.len();
+            scopes.remove(index);
+            // Guards are dropped in any order when unwinding, and panicking
+            // again would abort.
+            debug_assert!(
+                innermost || std::thread::panicking(),
+                "NodeIds guards must be dropped in the reverse order they were created"
+            );
+        });
+    }
 }
 
-/// Put this behind unsafe so that tests outside of this crate can reset the
-/// node id.
+/// Reset the process-global Id counter, used by nodes created outside of any
+/// [NodeIds] scope.
 ///
 /// This is unsafe to call because it will affect the node count to other
 /// co-existing graphs. Make sure you're running this test in `serial`.
@@ -29,6 +138,7 @@ pub fn reset_node_id() {
 ///
 /// Only for use in testing.
 #[allow(unused)]
+#[deprecated(note = "create test graphs in a `NodeIds` scope instead")]
 pub unsafe fn ext_reset_node_id() {
     NODE_ID.store(0, Ordering::Relaxed);
 }
@@ -49,17 +159,24 @@ where
     }
 }
 
+impl<T> Identifiable for Arc<T>
+where
+    T: Identifiable,
+{
+    fn id(&self) -> usize {
+        T::id(self)
+    }
+}
+
 #[cfg(all(test, not(miri)))]
 mod tests {
-    use serial_test::serial;
-
     use super::*;
     use crate::execution::{internal_test_utils::TestData, InputNode};
 
     #[test]
-    #[serial]
     fn test_identifiable_rc() {
-        reset_node_id();
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
         for i in 0..32 {
             let node = InputNode::new(TestData::new(i));
             let rc = Rc::new(node);
@@ -68,11 +185,83 @@ mod tests {
     }
 
     #[test]
-    #[serial]
     fn test_next_node_id() {
-        reset_node_id();
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
         for i in 0..32_usize {
             assert_eq!(next_node_id(), i);
         }
     }
+
+    #[test]
+    fn test_node_ids_nested() {
+        let outer = NodeIds::new();
+        let inner = NodeIds::new();
+        outer.scope(|| {
+            assert_eq!(next_node_id(), 0);
+            inner.scope(|| assert_eq!(next_node_id(), 0));
+            assert_eq!(next_node_id(), 1);
+            inner.scope(|| assert_eq!(next_node_id(), 1));
+        });
+        assert_eq!(outer.scope(next_node_id), 2);
+        // Outside of any scope, Ids are taken from the process-global counter.
+        assert!(SCOPES.with(|scopes| scopes.borrow().is_empty()));
+        let unscoped = next_node_id();
+        assert!(next_node_id() > unscoped);
+    }
+
+    #[test]
+    fn test_node_ids_panic() {
+        let ids = NodeIds::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            ids.scope(|| {
+                next_node_id();
+                panic!("building the graph failed");
+            })
+        }));
+        assert!(result.is_err());
+        assert!(SCOPES.with(|scopes| scopes.borrow().is_empty()));
+        assert_eq!(ids.scope(next_node_id), 1);
+    }
+
+    #[test]
+    fn test_node_ids_threads() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let mut created = std::thread::scope(|s| {
+            let threads = (0..4)
+                .map(|_| s.spawn(|| ids.scope(|| [next_node_id(), next_node_id()])))
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .flat_map(|thread| thread.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        created.sort_unstable();
+        assert_eq!(created, (0..8).collect::<Vec<_>>());
+        assert_eq!(next_node_id(), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "reverse order")]
+    fn test_node_ids_guard_order() {
+        let outer = NodeIds::new();
+        let inner = NodeIds::new();
+        let outer_guard = outer.enter();
+        let _inner_guard = inner.enter();
+        drop(outer_guard);
+    }
+
+    #[test]
+    fn test_node_ids_guard_order_panicking() {
+        let outer = NodeIds::new();
+        let inner = NodeIds::new();
+        let result = std::panic::catch_unwind(|| {
+            // The outer guard is dropped first whilst unwinding.
+            let _guards = (outer.enter(), inner.enter());
+            panic!("building the graph failed");
+        });
+        assert!(result.is_err());
+        assert!(SCOPES.with(|scopes| scopes.borrow().is_empty()));
+    }
 }
@@ -1,12 +1,19 @@
 use std::{
-    cell::{BorrowError, RefCell},
+    cell::{BorrowError, RefCell, RefMut},
+    fmt,
+    future::Future,
     ops::DerefMut,
     rc::Rc,
 };
 
 use crate::execution::{
-    error::ResolveResult, identifiable::next_node_id, Clean, Identifiable, InputState, Named,
-    NodeRef, NodeState, Resolve, UpdateInput, Visitor,
+    error::{
+        borrow_state, borrow_state_mut, trace_node, EarlyExit, ResolveError, ResolveResult, Traced,
+    },
+    identifiable::next_node_id,
+    node::{Dependees, Observers},
+    Arena, ArenaRef, AsyncResolve, Clean, Identifiable, InputState, Named, NodeChange, NodeHash,
+    NodeRef, NodeState, Resolve, Staleness, TryUpdateInput, UpdateInput, Visitor,
 };
 
 /// # Input Node
@@ -25,7 +32,12 @@ use crate::execution::{
 /// `InputNode` uses interior mutability to allow the wrapped value to be
 /// updated with a shared reference.
 ///
-/// To update the value, use [update](Self::update).
+/// To update the value, use [update](Self::update). This fails if the value
+/// is currently borrowed, for example by a [NodeRef] returned from
+/// [value](Self::value) or a resolve. Updates which must not fail can be
+/// [enqueued](Self::enqueue) instead, and are applied the next time this node
+/// is resolved or updated.
+///
 /// A node which can update values from outside of the graph via
 /// [update](Self::update).
 ///
@@ -63,6 +75,13 @@ pub struct InputNode<T> {
     resolve_state: RefCell<InputState>,
     /// The inner value of this node.
     value: RefCell<NodeState<T>>,
+    /// Updates waiting to be applied to the inner value, in the order they
+    /// were enqueued.
+    pending: Pending<T>,
+    /// Notified whenever the value changes during a resolve.
+    observers: Observers,
+    /// Marked stale whenever this node is updated.
+    dependees: Dependees,
     /// Unique runtime identifier.
     id: usize,
 }
@@ -90,31 +109,174 @@ where
 
     /// Create this node with a specified Id. Useful for tests.
     pub fn new_with_id(value: T, id: usize) -> Rc<Self> {
-        Rc::new(Self {
+        Rc::new(Self::build(value, id))
+    }
+
+    /// Wrap this leaf in a node allocated in `arena`.
+    pub fn new_in(arena: &Arena<Self>, value: T) -> ArenaRef<Self> {
+        arena.alloc(Self::build(value, next_node_id()))
+    }
+
+    fn build(value: T, id: usize) -> Self {
+        Self {
             resolve_state: RefCell::new(InputState::default()),
             value: RefCell::new(NodeState::new(value)),
+            pending: Pending::default(),
+            observers: Observers::default(),
+            dependees: Dependees::default(),
             id,
-        })
+        }
     }
 
     /// The public interface to provide data to mutate the inner value via
     /// a shared reference.
+    ///
+    /// Any [enqueued](Self::enqueue) updates are applied first.
     pub fn update(&self, input: T::Update) -> ResolveResult<()> {
-        let mut node_state = self.value.try_borrow_mut()?;
-        let mut resolve_state = self.resolve_state.try_borrow_mut()?;
-        // Flush any changes since it was resolved.
-        if *resolve_state == InputState::Resolving {
-            node_state.clean();
-        }
-        *resolve_state = InputState::Updating;
-        node_state.deref_mut().update_mut(input);
-        Ok(())
+        trace_node(self, None, || {
+            self.lock()?.apply(Some(input));
+            self.dependees.mark();
+            Ok(())
+        })
+    }
+
+    /// Like [update](Self::update), but the update is first checked with
+    /// [TryUpdateInput::validate_update]. If it is rejected, it isn't
+    /// applied.
+    ///
+    /// Any [enqueued](Self::enqueue) updates are applied first, whether or
+    /// not the update is rejected, so that it's checked against the value it
+    /// would be applied to.
+    pub fn try_update(&self, input: T::Update) -> ResolveResult<()>
+    where
+        T: TryUpdateInput,
+    {
+        trace_node(self, None, || {
+            let mut guard = self.lock()?;
+            // Enqueued updates have already marked the dependees.
+            guard.flush();
+            validate::<T>(&guard.node_state, &input)?;
+            guard.apply(Some(input));
+            self.dependees.mark();
+            Ok(())
+        })
+    }
+
+    /// Queue an update to the inner value. Unlike [update](Self::update),
+    /// this succeeds even whilst the value is borrowed.
+    ///
+    /// Queued updates are applied, in order, at the start of the next
+    /// resolve of this node, or the next call to `update`. Each is boxed
+    /// until then.
+    pub fn enqueue(&self, input: T::Update)
+    where
+        T::Update: 'static,
+    {
+        self.pending
+            .0
+            .borrow_mut()
+            .push(Box::new(move |value: &mut T| value.update_mut(input)));
+        self.dependees.mark();
+    }
+
+    /// Call `observer` whenever this node is resolved and its [NodeHash]
+    /// has changed. A value which isn't hashed is considered changed each
+    /// time it's resolved after an update.
+    ///
+    /// [NodeHash]: crate::NodeHash
+    pub fn subscribe(&self, observer: impl FnMut(&NodeChange) + 'static) {
+        self.observers.subscribe(observer);
+    }
+
+    /// Increment the version of a [versioned](crate::HashValue::VERSIONED)
+    /// value, without updating it. Any [enqueued](Self::enqueue) updates are
+    /// applied first.
+    pub fn mark_changed(&self) -> ResolveResult<()> {
+        trace_node(self, None, || {
+            let mut guard = self.lock()?;
+            guard.flush();
+            guard.node_state.mark_changed();
+            self.dependees.mark();
+            Ok(())
+        })
     }
 
     /// Access the inner value.
     pub fn value(&self) -> Result<NodeRef<'_, T>, BorrowError> {
         self.value.try_borrow()
     }
+
+    /// Take exclusive access to this node, so that updates can be applied.
+    pub(super) fn lock(&self) -> ResolveResult<UpdateGuard<'_, T>> {
+        Ok(UpdateGuard {
+            node_state: borrow_state_mut(&self.value)?,
+            resolve_state: self.resolve_state.try_borrow_mut()?,
+            pending: &self.pending,
+        })
+    }
+}
+
+/// Check `input` with [TryUpdateInput::validate_update].
+pub(super) fn validate<T: TryUpdateInput>(value: &T, input: &T::Update) -> ResolveResult<()> {
+    value
+        .validate_update(input)
+        .map_err(|e| ResolveError::Rejected(Traced::new(EarlyExit::custom(e))))
+}
+
+/// An update waiting to be applied to the value of an [InputNode].
+type PendingUpdate<T> = Box<dyn FnOnce(&mut T)>;
+
+/// Updates waiting to be applied to the value of an [InputNode]. They are
+/// stored as closures so that `InputNode<T>` needn't name `T::Update`.
+struct Pending<T>(RefCell<Vec<PendingUpdate<T>>>);
+
+impl<T> Default for Pending<T> {
+    fn default() -> Self {
+        Self(RefCell::default())
+    }
+}
+
+impl<T> fmt::Debug for Pending<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pending")
+            .field("len", &self.0.borrow().len())
+            .finish()
+    }
+}
+
+/// Exclusive access to the state of an [InputNode].
+pub(super) struct UpdateGuard<'a, T> {
+    node_state: RefMut<'a, NodeState<T>>,
+    resolve_state: RefMut<'a, InputState>,
+    pending: &'a Pending<T>,
+}
+
+impl<T: UpdateInput> UpdateGuard<'_, T> {
+    /// Apply any enqueued updates to the inner value, first flushing any
+    /// changes since it was resolved.
+    pub(super) fn flush(&mut self) {
+        self.apply(None);
+    }
+
+    /// Apply any enqueued updates, then `input`, to the inner value, first
+    /// flushing any changes since it was resolved.
+    pub(super) fn apply(&mut self, input: Option<T::Update>) {
+        let pending = self.pending.0.take();
+        if pending.is_empty() && input.is_none() {
+            return;
+        }
+        if *self.resolve_state == InputState::Resolving {
+            self.node_state.clean();
+        }
+        *self.resolve_state = InputState::Updating;
+        for update in pending {
+            update(self.node_state.deref_mut());
+        }
+        if let Some(input) = input {
+            self.node_state.deref_mut().update_mut(input);
+        }
+        self.node_state.mark_changed();
+    }
 }
 
 impl<T> Resolve for InputNode<T>
@@ -127,23 +289,295 @@ where
         Self: 'a;
 
     fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
-        visitor.touch(self, None);
-        if visitor.visit(self) {
-            let mut node_state = self.value.try_borrow_mut()?;
-            let mut resolve_state = self.resolve_state.try_borrow_mut()?;
-            // Ensures `update` changes are only flushed once.
-            match *resolve_state {
-                InputState::Updating => *resolve_state = InputState::Resolving,
-                InputState::Resolving => {
-                    node_state.clean();
-                    *resolve_state = InputState::Resolved
+        trace_node(self, None, || {
+            visitor.touch(self, None);
+            if visitor.visit(self) {
+                let mut guard = self.lock()?;
+                guard.flush();
+                // Ensures `update` changes are only flushed once.
+                let resolve_state = *guard.resolve_state;
+                match resolve_state {
+                    InputState::Updating => *guard.resolve_state = InputState::Resolving,
+                    InputState::Resolving => {
+                        guard.node_state.clean();
+                        *guard.resolve_state = InputState::Resolved
+                    }
+                    InputState::Resolved => {}
+                }
+                // The hash is only set when this node is being read.
+                let previous = guard.node_state.node_hash();
+                guard.node_state.update_node_hash(&mut visitor.hasher());
+                let current = guard.node_state.node_hash();
+                drop(guard);
+                // Dependees must resolve this node again for it to be
+                // cleaned, and once more if cleaning changed it.
+                let changed = match resolve_state {
+                    InputState::Updating => true,
+                    InputState::Resolving => previous != current,
+                    InputState::Resolved => false,
+                };
+                if changed {
+                    self.dependees.mark();
+                }
+                // Cleaning a value which isn't hashed isn't reported as a
+                // change, so observers aren't notified twice for an update.
+                let cleaned = matches!(current, NodeHash::NotHashed)
+                    && resolve_state == InputState::Resolving;
+                if changed && !cleaned {
+                    self.observers.notify(self, previous, current);
                 }
-                InputState::Resolved => {}
             }
-            // The hash is only set when this node is being read.
-            node_state.update_node_hash(&mut visitor.hasher());
+            visitor.leave(self);
+            borrow_state(&self.value)
+        })
+    }
+
+    fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        self.dependees.subscribe(dependee);
+        true
+    }
+}
+
+impl<T> AsyncResolve for InputNode<T>
+where
+    T: UpdateInput,
+{
+    type Output<'a>
+        = NodeRef<'a, T>
+    where
+        Self: 'a;
+
+    fn resolve_async(
+        &self,
+        visitor: &mut impl Visitor,
+    ) -> impl Future<Output = ResolveResult<Self::Output<'_>>> {
+        std::future::ready(Resolve::resolve(self, visitor))
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::execution::{internal_test_utils::TestData, HashValue, NodeHash, NodeIds};
+
+    #[derive(Debug, PartialEq)]
+    pub struct TooLarge;
+
+    impl std::fmt::Display for TooLarge {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("too large")
+        }
+    }
+
+    impl std::error::Error for TooLarge {}
+
+    impl TryUpdateInput for TestData {
+        type Error = TooLarge;
+
+        fn validate_update(&self, update: &Self::Update) -> Result<(), Self::Error> {
+            if self.inner + *update > 10 {
+                Err(TooLarge)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn test_enqueue() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(0));
+        let mut visitor = HashSet::<usize>::new();
+
+        let value = input.resolve_root(&mut visitor).unwrap();
+        assert!(input.update(1).is_err());
+        input.enqueue(1);
+        input.enqueue(2);
+        // Nothing is applied whilst the value is borrowed.
+        assert_eq!(value.inner, 0);
+        drop(value);
+
+        let value = input.resolve_root(&mut visitor).unwrap();
+        assert_eq!(value.inner, 2);
+        assert_eq!(value.recent, vec![0, 1]);
+        drop(value);
+
+        // Resolving again flushes the changes exactly once.
+        let value = input.resolve_root(&mut visitor).unwrap();
+        assert_eq!(value.inner, 2);
+        assert!(value.recent.is_empty());
+        drop(value);
+
+        // Enqueued updates are applied before a direct update.
+        input.enqueue(3);
+        input.update(4).unwrap();
+        assert_eq!(input.value().unwrap().inner, 4);
+        assert_eq!(input.value().unwrap().recent, vec![2, 3]);
+        let value = input.resolve_root(&mut visitor).unwrap();
+        assert_eq!(value.inner, 4);
+        assert_eq!(value.recent, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_try_update() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(0));
+        let mut visitor = HashSet::<usize>::new();
+
+        input.try_update(1).unwrap();
+        drop(input.resolve_root(&mut visitor).unwrap());
+        assert_eq!(*input.resolve_state.borrow(), InputState::Resolving);
+
+        let err = input.try_update(11).unwrap_err();
+        let ResolveError::Rejected(ref rejected) = err else {
+            panic!("expected a rejected update");
+        };
+        assert_eq!(rejected.downcast_ref::<TooLarge>(), Some(&TooLarge));
+        assert_eq!(err.to_string(), "rejected update: too large at TestData#0");
+        // Neither the value nor the resolve state have changed.
+        assert_eq!(
+            **input.value().unwrap(),
+            TestData {
+                inner: 1,
+                recent: vec![0]
+            }
+        );
+        assert_eq!(*input.resolve_state.borrow(), InputState::Resolving);
+
+        input.try_update(2).unwrap();
+        assert_eq!(
+            **input.value().unwrap(),
+            TestData {
+                inner: 2,
+                recent: vec![1]
+            }
+        );
+        assert_eq!(*input.resolve_state.borrow(), InputState::Updating);
+    }
+
+    #[test]
+    fn test_try_update_enqueued() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(TestData::new(0));
+        let mut visitor = HashSet::<usize>::new();
+        drop(input.resolve_root(&mut visitor).unwrap());
+
+        // The update is checked against the value it would be applied to,
+        // after the enqueued update.
+        input.enqueue(6);
+        let err = input.try_update(5).unwrap_err();
+        assert!(matches!(err, ResolveError::Rejected(_)));
+        // Only the enqueued update has been applied.
+        assert_eq!(
+            **input.value().unwrap(),
+            TestData {
+                inner: 6,
+                recent: vec![0]
+            }
+        );
+        assert_eq!(*input.resolve_state.borrow(), InputState::Updating);
+
+        input.try_update(4).unwrap();
+        assert_eq!(input.resolve_root(&mut visitor).unwrap().inner, 4);
+    }
+
+    struct Unhashed(u32);
+
+    impl Named for Unhashed {
+        fn name() -> &'static str {
+            "Unhashed"
+        }
+    }
+
+    impl HashValue for Unhashed {
+        fn hash_value(&self, _: &mut impl std::hash::Hasher) -> NodeHash {
+            NodeHash::NotHashed
+        }
+    }
+
+    impl Clean for Unhashed {
+        fn clean(&mut self) {}
+    }
+
+    impl UpdateInput for Unhashed {
+        type Update = u32;
+
+        fn update_mut(&mut self, update: Self::Update) {
+            self.0 = update;
         }
-        visitor.leave(self);
-        Ok(self.value.try_borrow()?)
+    }
+
+    #[test]
+    fn test_subscribe_not_hashed() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(Unhashed(0));
+        let changes = Rc::new(RefCell::new(0));
+        input.subscribe({
+            let changes = Rc::clone(&changes);
+            move |_| *changes.borrow_mut() += 1
+        });
+        let mut visitor = HashSet::<usize>::new();
+        let mut resolve = || drop(input.resolve_root(&mut visitor).unwrap());
+
+        // Observers are notified once per update, however often it's resolved.
+        (0..3).for_each(|_| resolve());
+        assert_eq!(*changes.borrow(), 1);
+        input.update(1).unwrap();
+        (0..3).for_each(|_| resolve());
+        assert_eq!(*changes.borrow(), 2);
+    }
+
+    struct Versioned(u32);
+
+    impl Named for Versioned {
+        fn name() -> &'static str {
+            "Versioned"
+        }
+    }
+
+    impl HashValue for Versioned {
+        const VERSIONED: bool = true;
+
+        fn hash_value(&self, _: &mut impl std::hash::Hasher) -> NodeHash {
+            NodeHash::NotHashed
+        }
+    }
+
+    impl Clean for Versioned {
+        fn clean(&mut self) {}
+    }
+
+    impl UpdateInput for Versioned {
+        type Update = u32;
+
+        fn update_mut(&mut self, update: Self::Update) {
+            self.0 = update;
+        }
+    }
+
+    #[test]
+    fn test_mark_changed() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let input = InputNode::new(Versioned(0));
+        let mut visitor = HashSet::<usize>::new();
+        let mut node_hash = || input.resolve_root(&mut visitor).unwrap().node_hash();
+
+        assert_eq!(node_hash(), NodeHash::Hashed(0));
+        assert_eq!(node_hash(), NodeHash::Hashed(0));
+        input.update(1).unwrap();
+        assert_eq!(node_hash(), NodeHash::Hashed(1));
+        input.mark_changed().unwrap();
+        assert_eq!(node_hash(), NodeHash::Hashed(2));
+        // Enqueued updates are versioned as one.
+        input.enqueue(2);
+        input.enqueue(3);
+        assert_eq!(node_hash(), NodeHash::Hashed(3));
     }
 }
@@ -1,5 +1,9 @@
 mod input_node;
 mod input_state;
+mod sync_input_node;
+mod transaction;
 
 pub use input_node::InputNode;
 pub use input_state::InputState;
+pub use sync_input_node::SyncInputNode;
+pub use transaction::{StagedUpdates, Transaction};
@@ -0,0 +1,157 @@
+use std::{
+    ops::DerefMut,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use crate::execution::{
+    error::{trace_node, ResolveResult},
+    identifiable::next_node_id,
+    node::SyncObservers,
+    Clean, Identifiable, InputState, Named, NodeChange, NodeHash, NodeState, Resolve, SyncNodeRef,
+    UpdateInput, Visitor,
+};
+
+/// # Sync Input Node
+///
+/// A thread-safe equivalent of an [InputNode](crate::InputNode). The wrapped
+/// value is held behind an [RwLock] and the node is constructed in an [Arc],
+/// allowing it to be shared between threads.
+///
+/// Updating or resolving this node waits for any other thread holding a
+/// read-reference to its value, such as a dependee being recalculated.
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use depends::SyncInputNode;
+/// let input = SyncInputNode::new(5_i32);
+///
+/// let handle = std::thread::spawn({
+///     let input = Arc::clone(&input);
+///     move || input.update(6).unwrap()
+/// });
+/// handle.join().unwrap();
+///
+/// assert_eq!(*input.value().unwrap().value(), 6);
+/// ```
+#[derive(Debug)]
+pub struct SyncInputNode<T> {
+    /// The resolve state of this node. This is used to ensure that a
+    /// node is cleaned only once per resolve.
+    resolve_state: Mutex<InputState>,
+    /// The inner value of this node.
+    value: RwLock<NodeState<T>>,
+    /// Notified whenever the value changes during a resolve.
+    observers: SyncObservers,
+    /// Unique runtime identifier.
+    id: usize,
+}
+
+impl<T: Named> Named for SyncInputNode<T> {
+    fn name() -> &'static str {
+        T::name()
+    }
+}
+
+impl<T: Named> Identifiable for SyncInputNode<T> {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<T> SyncInputNode<T>
+where
+    T: UpdateInput,
+{
+    /// Wrap this leaf in a node.
+    pub fn new(value: T) -> Arc<Self> {
+        Self::new_with_id(value, next_node_id())
+    }
+
+    /// Create this node with a specified Id. Useful for tests.
+    pub fn new_with_id(value: T, id: usize) -> Arc<Self> {
+        Arc::new(Self {
+            resolve_state: Mutex::new(InputState::default()),
+            value: RwLock::new(NodeState::new(value)),
+            observers: SyncObservers::default(),
+            id,
+        })
+    }
+
+    /// The public interface to provide data to mutate the inner value via
+    /// a shared reference.
+    pub fn update(&self, input: T::Update) -> ResolveResult<()> {
+        trace_node(self, None, || {
+            let mut node_state = self.value.write()?;
+            let mut resolve_state = self.resolve_state.lock()?;
+            // Flush any changes since it was resolved.
+            if *resolve_state == InputState::Resolving {
+                node_state.clean();
+            }
+            *resolve_state = InputState::Updating;
+            node_state.deref_mut().update_mut(input);
+            node_state.mark_changed();
+            Ok(())
+        })
+    }
+
+    /// Call `observer` whenever this node is resolved and its [NodeHash]
+    /// has changed. A value which isn't hashed is considered changed each
+    /// time it's resolved after an update.
+    ///
+    /// Observers are called on the resolving thread, once the value is no
+    /// longer being written to.
+    pub fn subscribe(&self, observer: impl FnMut(&NodeChange) + Send + 'static) {
+        self.observers.subscribe(observer);
+    }
+
+    /// Access the inner value.
+    pub fn value(&self) -> ResolveResult<SyncNodeRef<'_, T>> {
+        Ok(self.value.read()?)
+    }
+}
+
+impl<T> Resolve for SyncInputNode<T>
+where
+    T: UpdateInput,
+{
+    type Output<'a>
+        = SyncNodeRef<'a, T>
+    where
+        Self: 'a;
+
+    fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        trace_node(self, None, || {
+            visitor.touch(self, None);
+            if visitor.visit(self) {
+                let mut node_state = self.value.write()?;
+                let mut resolve_state = self.resolve_state.lock()?;
+                // Ensures `update` changes are only flushed once.
+                let updated = match *resolve_state {
+                    InputState::Updating => {
+                        *resolve_state = InputState::Resolving;
+                        true
+                    }
+                    InputState::Resolving => {
+                        node_state.clean();
+                        *resolve_state = InputState::Resolved;
+                        false
+                    }
+                    InputState::Resolved => false,
+                };
+                // The hash is only set when this node is being read.
+                let previous = node_state.node_hash();
+                node_state.update_node_hash(&mut visitor.hasher());
+                let current = node_state.node_hash();
+                drop(resolve_state);
+                drop(node_state);
+                // See `InputNode::resolve` for why cleaning a value which
+                // isn't hashed isn't reported.
+                if updated || !matches!(current, NodeHash::NotHashed) {
+                    self.observers.notify(self, previous, current);
+                }
+            }
+            visitor.leave(self);
+            Ok(self.value.read()?)
+        })
+    }
+}
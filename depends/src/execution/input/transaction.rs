@@ -0,0 +1,428 @@
+use std::any::Any;
+
+use super::input_node::{validate, UpdateGuard};
+use crate::execution::{
+    error::{trace_node, EarlyExit, ResolveError, ResolveResult, Traced},
+    Identifiable, InputNode, TryUpdateInput, UpdateInput,
+};
+
+/// # Transaction
+///
+/// Stage updates to any number of [InputNode]s, and apply them all at once
+/// with [commit](Self::commit). A resolve will either see every update in
+/// the transaction, or none of them.
+///
+/// Updates are only applied once every node in the transaction has been
+/// borrowed, the batch has been [validated](Self::validate) and every
+/// [checked](Self::try_update) update has been accepted. If any of these
+/// fail, the staged updates are discarded.
+///
+/// Any [enqueued](InputNode::enqueue) updates to the nodes in the transaction
+/// are applied first, whether or not it's committed, so that the batch is
+/// checked against the values it would be applied to.
+///
+/// ```
+/// # use depends::{InputNode, Transaction, UpdateInput};
+/// # use depends::derives::Value;
+/// # use depends::error::{EarlyExit, ResolveError};
+/// # #[derive(Value, Hash)]
+/// # struct Price {
+/// #     value: i32,
+/// # }
+/// # impl UpdateInput for Price {
+/// #     type Update = i32;
+/// #     fn update_mut(&mut self, update: Self::Update) {
+/// #         self.value = update;
+/// #     }
+/// # }
+/// let bid = InputNode::new(Price { value: 99 });
+/// let ask = InputNode::new(Price { value: 101 });
+///
+/// let mut tx = Transaction::new();
+/// tx.update(&bid, 100).update(&ask, 102);
+/// tx.commit().unwrap();
+///
+/// assert_eq!(bid.value().unwrap().value, 100);
+/// assert_eq!(ask.value().unwrap().value, 102);
+///
+/// // A crossed market is rejected, and neither price changes.
+/// let mut tx = Transaction::new();
+/// tx.update(&bid, 103).update(&ask, 102).validate(|staged| {
+///     // Either price may be left as it is.
+///     let new_bid = staged.last(&bid).copied();
+///     let new_ask = staged.last(&ask).copied();
+///     let new_bid = new_bid.unwrap_or_else(|| bid.value().unwrap().value);
+///     let new_ask = new_ask.unwrap_or_else(|| ask.value().unwrap().value);
+///     if new_bid < new_ask {
+///         Ok(())
+///     } else {
+///         Err(EarlyExit::new("crossed"))
+///     }
+/// });
+/// assert!(matches!(tx.commit(), Err(ResolveError::Rejected(_))));
+///
+/// assert_eq!(bid.value().unwrap().value, 100);
+/// assert_eq!(ask.value().unwrap().value, 102);
+/// ```
+#[derive(Default)]
+pub struct Transaction<'a> {
+    /// The updates to apply, in the order they were staged.
+    staged: Vec<Box<dyn Staged<'a> + 'a>>,
+    /// Run before any node is borrowed, to accept or reject the batch.
+    validate: Option<Validate<'a>>,
+}
+
+type Validate<'a> = Box<dyn FnOnce(&StagedUpdates<'_, 'a>) -> Result<(), EarlyExit> + 'a>;
+
+impl<'a> Transaction<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stage an update to `node`. Nothing is applied until the transaction
+    /// is committed.
+    pub fn update<T>(&mut self, node: &'a InputNode<T>, update: T::Update) -> &mut Self
+    where
+        T: UpdateInput,
+        T::Update: 'static,
+    {
+        self.staged.push(Box::new(StagedUpdate {
+            node,
+            update,
+            check: None,
+        }));
+        self
+    }
+
+    /// Stage an update to `node`, which is checked with
+    /// [TryUpdateInput::validate_update] on [commit](Self::commit). If it is
+    /// rejected, the whole transaction is rolled back.
+    ///
+    /// Each update is checked against the value of `node` with any enqueued
+    /// updates applied. An update staged after another to the same node
+    /// would be applied to a value which can't be checked until the
+    /// transaction is committed, so it's rejected.
+    pub fn try_update<T>(&mut self, node: &'a InputNode<T>, update: T::Update) -> &mut Self
+    where
+        T: TryUpdateInput,
+        T::Update: 'static,
+    {
+        self.staged.push(Box::new(StagedUpdate {
+            node,
+            update,
+            check: Some(validate::<T>),
+        }));
+        self
+    }
+
+    /// Provide a hook which can reject the batch. It is called on
+    /// [commit](Self::commit) with the [staged updates](StagedUpdates),
+    /// once any enqueued updates have been applied but before any node is
+    /// borrowed, so it may also read the current [value](InputNode::value)
+    /// of any node. Its error is returned as
+    /// [Rejected](crate::error::ResolveError::Rejected).
+    pub fn validate(
+        &mut self,
+        hook: impl FnOnce(&StagedUpdates<'_, 'a>) -> Result<(), EarlyExit> + 'a,
+    ) -> &mut Self {
+        self.validate = Some(Box::new(hook));
+        self
+    }
+
+    /// Apply every staged update. If the batch is rejected, any update is
+    /// rejected or any node cannot be borrowed, no updates are applied.
+    pub fn commit(self) -> ResolveResult<()> {
+        // A node which is updated more than once is only borrowed once.
+        let first = self
+            .staged
+            .iter()
+            .enumerate()
+            .map(|(i, staged)| self.staged[..i].iter().all(|s| s.id() != staged.id()))
+            .collect::<Vec<_>>();
+        for (staged, _) in self.staged.iter().zip(&first).filter(|(_, first)| **first) {
+            staged.lock()?.flush();
+        }
+        if let Some(validate) = self.validate {
+            validate(&StagedUpdates(&self.staged))
+                .map_err(|e| ResolveError::Rejected(Traced::new(e)))?;
+        }
+        for (staged, first) in self.staged.iter().zip(&first) {
+            staged.check(*first)?;
+        }
+        let mut guards = Vec::with_capacity(self.staged.len());
+        for (staged, _) in self.staged.iter().zip(&first).filter(|(_, first)| **first) {
+            guards.push(staged.lock()?);
+        }
+        // Every node is borrowed, so nothing can fail from here.
+        for staged in self.staged {
+            staged.enqueue();
+        }
+        for mut guard in guards {
+            guard.flush();
+        }
+        Ok(())
+    }
+}
+
+/// The updates staged in a [Transaction], passed to its
+/// [validate](Transaction::validate) hook.
+pub struct StagedUpdates<'s, 'a>(&'s [Box<dyn Staged<'a> + 'a>]);
+
+impl<'s> StagedUpdates<'s, '_> {
+    /// Every update staged to `node`, in the order they were staged.
+    pub fn get<T>(&self, node: &InputNode<T>) -> Vec<&'s T::Update>
+    where
+        T: UpdateInput,
+        T::Update: 'static,
+    {
+        self.0
+            .iter()
+            .filter_map(|staged| Self::downcast(staged.as_ref(), node))
+            .collect()
+    }
+
+    /// The last update staged to `node`, if any.
+    pub fn last<T>(&self, node: &InputNode<T>) -> Option<&'s T::Update>
+    where
+        T: UpdateInput,
+        T::Update: 'static,
+    {
+        self.0
+            .iter()
+            .rev()
+            .find_map(|staged| Self::downcast(staged.as_ref(), node))
+    }
+
+    fn downcast<'b, T>(
+        staged: &'b (dyn Staged<'_> + '_),
+        node: &InputNode<T>,
+    ) -> Option<&'b T::Update>
+    where
+        T: UpdateInput,
+        T::Update: 'static,
+    {
+        let node = node as *const InputNode<T> as *const ();
+        if std::ptr::eq(staged.node(), node) {
+            staged.update().downcast_ref()
+        } else {
+            None
+        }
+    }
+
+    /// The number of updates staged.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether no updates are staged.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// An update to an [InputNode] of any type.
+trait Staged<'a> {
+    fn id(&self) -> usize;
+
+    /// The address of the node, to identify it regardless of its type.
+    fn node(&self) -> *const ();
+
+    fn update(&self) -> &dyn Any;
+
+    /// Whether the update would be accepted by the node, if it's the `first`
+    /// staged to it.
+    fn check(&self, first: bool) -> ResolveResult<()>;
+
+    fn lock(&self) -> ResolveResult<Box<dyn Flush + 'a>>;
+
+    fn enqueue(self: Box<Self>);
+}
+
+trait Flush {
+    fn flush(&mut self);
+}
+
+struct StagedUpdate<'a, T: UpdateInput> {
+    node: &'a InputNode<T>,
+    update: T::Update,
+    /// Set if the update must be accepted by the node.
+    check: Option<Check<T>>,
+}
+
+/// Checks whether an update would be accepted by the value of a node.
+type Check<T> = fn(&T, &<T as UpdateInput>::Update) -> ResolveResult<()>;
+
+impl<'a, T> Staged<'a> for StagedUpdate<'a, T>
+where
+    T: UpdateInput,
+    T::Update: 'static,
+{
+    fn id(&self) -> usize {
+        self.node.id()
+    }
+
+    fn node(&self) -> *const () {
+        self.node as *const InputNode<T> as *const ()
+    }
+
+    fn update(&self) -> &dyn Any {
+        &self.update
+    }
+
+    fn check(&self, first: bool) -> ResolveResult<()> {
+        let Some(check) = self.check else {
+            return Ok(());
+        };
+        let node = self.node;
+        trace_node(node, None, || {
+            if !first {
+                return Err(ResolveError::Rejected(Traced::new(EarlyExit::new(
+                    "a checked update must be the first staged to its node",
+                ))));
+            }
+            let value = node.value()?;
+            check(&value, &self.update)
+        })
+    }
+
+    fn lock(&self) -> ResolveResult<Box<dyn Flush + 'a>> {
+        let node = self.node;
+        trace_node(node, None, || Ok(Box::new(node.lock()?) as Box<dyn Flush>))
+    }
+
+    fn enqueue(self: Box<Self>) {
+        self.node.enqueue(self.update);
+    }
+}
+
+impl<T: UpdateInput> Flush for UpdateGuard<'_, T> {
+    fn flush(&mut self) {
+        UpdateGuard::flush(self);
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        error::NodeFrame,
+        execution::{internal_test_utils::TestData, NodeIds, Resolve},
+    };
+
+    #[test]
+    fn test_transaction() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let a = InputNode::new(TestData::new(0));
+        let b = InputNode::new(TestData::new(0));
+        let mut visitor = HashSet::<usize>::new();
+
+        let mut tx = Transaction::new();
+        tx.update(&a, 1).update(&b, 2).update(&a, 3);
+        tx.commit().unwrap();
+        assert_eq!(a.value().unwrap().inner, 3);
+        assert_eq!(a.value().unwrap().recent, vec![0, 1]);
+        assert_eq!(b.value().unwrap().inner, 2);
+
+        // A node which can't be borrowed aborts the whole transaction.
+        let value = b.resolve_root(&mut visitor).unwrap();
+        let mut tx = Transaction::new();
+        tx.update(&a, 4).update(&b, 5);
+        let err = tx.commit().unwrap_err();
+        assert!(matches!(err, ResolveError::OutstandingBorrow(_)));
+        assert_eq!(err.node(), Some(&NodeFrame::new(b.as_ref(), None)));
+        drop(value);
+        assert_eq!(a.value().unwrap().inner, 3);
+        assert_eq!(b.value().unwrap().inner, 2);
+
+        // As does a rejected batch.
+        let mut tx = Transaction::new();
+        tx.update(&a, 6)
+            .validate(|_| Err(EarlyExit::new("rejected")));
+        assert!(matches!(tx.commit(), Err(ResolveError::Rejected(_))));
+        assert_eq!(a.value().unwrap().inner, 3);
+
+        // Nothing left over is applied on the next resolve.
+        assert_eq!(a.resolve_root(&mut visitor).unwrap().inner, 3);
+        assert_eq!(b.resolve_root(&mut visitor).unwrap().inner, 2);
+    }
+
+    #[test]
+    fn test_transaction_staged() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let a = InputNode::new(TestData::new(0));
+        let b = InputNode::new(TestData::new(0));
+        let c = InputNode::new(TestData::new(0));
+
+        // The hook sees every staged update.
+        let mut tx = Transaction::new();
+        tx.update(&a, 1)
+            .update(&b, 2)
+            .update(&a, 3)
+            .validate(|staged| {
+                assert_eq!(staged.len(), 3);
+                assert_eq!(staged.get(&a), vec![&1, &3]);
+                assert_eq!(staged.last(&b), Some(&2));
+                assert_eq!(staged.last(&c), None);
+                Err(EarlyExit::new("rejected"))
+            });
+        assert!(matches!(tx.commit(), Err(ResolveError::Rejected(_))));
+        assert_eq!(a.value().unwrap().inner, 0);
+        assert_eq!(b.value().unwrap().inner, 0);
+    }
+
+    #[test]
+    fn test_transaction_try_update() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let a = InputNode::new(TestData::new(0));
+        let b = InputNode::new(TestData::new(0));
+        let mut visitor = HashSet::<usize>::new();
+
+        let mut tx = Transaction::new();
+        tx.try_update(&a, 1).try_update(&b, 2);
+        tx.commit().unwrap();
+        assert_eq!(a.value().unwrap().inner, 1);
+        assert_eq!(b.value().unwrap().inner, 2);
+
+        // A rejected update rolls back the whole batch.
+        let mut tx = Transaction::new();
+        tx.update(&a, 3).try_update(&b, 11);
+        let err = tx.commit().unwrap_err();
+        assert!(matches!(err, ResolveError::Rejected(_)));
+        assert_eq!(err.to_string(), "rejected update: too large at TestData#1");
+        assert_eq!(a.value().unwrap().inner, 1);
+        assert_eq!(b.value().unwrap().inner, 2);
+        assert_eq!(a.resolve_root(&mut visitor).unwrap().inner, 1);
+
+        // Updates are checked against the value with any enqueued updates
+        // applied, which are applied even if the batch is rejected.
+        b.enqueue(6);
+        let mut tx = Transaction::new();
+        tx.update(&a, 3).try_update(&b, 5);
+        assert!(matches!(tx.commit(), Err(ResolveError::Rejected(_))));
+        assert_eq!(a.value().unwrap().inner, 1);
+        assert_eq!(b.value().unwrap().inner, 6);
+
+        // As is the hook.
+        a.enqueue(4);
+        let mut tx = Transaction::new();
+        tx.update(&a, 5).validate(|_| {
+            assert_eq!(a.value().unwrap().inner, 4);
+            Ok(())
+        });
+        tx.commit().unwrap();
+        assert_eq!(a.value().unwrap().inner, 5);
+
+        // An update can't be checked against a value staged in the same
+        // transaction.
+        let mut tx = Transaction::new();
+        tx.update(&a, 0).try_update(&a, 1);
+        let err = tx.commit().unwrap_err();
+        assert!(matches!(err, ResolveError::Rejected(_)));
+        assert_eq!(a.value().unwrap().inner, 5);
+    }
+}
@@ -1,10 +1,16 @@
-use std::hash::{Hash, Hasher};
+use std::{
+    future::Future,
+    hash::{Hash, Hasher},
+    pin::{pin, Pin},
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+};
 
-use crate::{Clean, HashValue, Named, NodeHash, UpdateInput};
+use crate::{Clean, HashValue, Named, NodeHash, UpdateFn, UpdateInput};
 
 /// A test node which pushes old values to a `recent` vector and replaces
 /// `inner` with the new value.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct TestData {
     pub inner: u32,
     pub recent: Vec<u32>,
@@ -38,6 +44,8 @@ impl Clean for TestData {
     }
 }
 
+impl UpdateFn for TestData {}
+
 impl UpdateInput for TestData {
     type Update = u32;
 
@@ -47,6 +55,42 @@ impl UpdateInput for TestData {
     }
 }
 
+/// A waker which does nothing, for polling futures to completion in tests.
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// Drive a future to completion on the current thread.
+pub fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(res) = fut.as_mut().poll(&mut cx) {
+            return res;
+        }
+    }
+}
+
+/// A future which is pending the first time it is polled.
+#[derive(Default)]
+pub struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            Poll::Pending
+        }
+    }
+}
+
 #[test]
 fn test_test_data() {
     // Unfortunately coverage requires us to test our tests
@@ -1,7 +1,10 @@
+mod arena;
 mod clean;
 mod dependency;
 mod derived;
+mod dyn_node;
 pub mod error;
+mod fn_operation;
 mod hash_value;
 mod identifiable;
 mod input;
@@ -10,23 +13,37 @@ mod named;
 mod node;
 mod primitives;
 mod resolve;
+mod resolve_async;
+mod select;
 mod update_derived;
+mod update_derived_async;
 mod update_input;
 mod visitor;
 
+pub use arena::{Arena, ArenaRef};
 pub use clean::Clean;
 pub use dependency::*;
-pub use derived::DerivedNode;
+pub use derived::{
+    AsyncDerivedNode, DerivedNode, MultiDerivedNode, OutputAt, OutputNode, Outputs, Outputs2,
+    Outputs3, Outputs4, Outputs5, Outputs6, Outputs7, Outputs8, SyncDerivedNode,
+};
+pub use dyn_node::DynNode;
+pub use fn_operation::{FnOperation, UpdateFn};
 pub use hash_value::HashValue;
-pub use identifiable::{next_node_id, Identifiable};
-pub use input::{InputNode, InputState};
+pub use identifiable::{next_node_id, Identifiable, NodeIds, NodeIdsGuard};
+pub use input::{InputNode, InputState, StagedUpdates, SyncInputNode, Transaction};
 pub use is_dirty::IsDirty;
 pub use named::Named;
-pub use node::{NodeHash, NodeRef, NodeState};
+pub use node::{NodeChange, NodeHash, NodeRef, NodeState, Staleness, SyncNodeRef};
 pub use resolve::Resolve;
+pub use resolve_async::AsyncResolve;
+pub use select::{DepSelect, Select, Selected};
 pub use update_derived::UpdateDerived;
-pub use update_input::UpdateInput;
-pub use visitor::{DiagnosticVisitor, HashSetVisitor, Visitor};
+pub use update_derived_async::AsyncUpdateDerived;
+pub use update_input::{TryUpdateInput, UpdateInput};
+pub use visitor::{
+    BitSetVisitor, DiagnosticVisitor, EpochVisitor, HashSetVisitor, ParallelVisitor, Visitor,
+};
 
 #[cfg(feature = "graphviz")]
 mod graph_create;
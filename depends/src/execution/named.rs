@@ -1,15 +1,36 @@
-use std::rc::Rc;
+use std::{rc::Rc, sync::Arc};
 
 /// A string name for each graph node, useful for rendering graph
 /// visualisations.
 pub trait Named {
     fn name() -> &'static str;
+
+    /// The name of this particular value, which is [Named::name] unless it
+    /// differs between values of the same type, such as the label of an
+    /// [FnOperation](crate::FnOperation).
+    fn label(&self) -> &'static str {
+        Self::name()
+    }
 }
 
 impl<T: Named> Named for Rc<T> {
     fn name() -> &'static str {
         T::name()
     }
+
+    fn label(&self) -> &'static str {
+        T::label(self)
+    }
+}
+
+impl<T: Named> Named for Arc<T> {
+    fn name() -> &'static str {
+        T::name()
+    }
+
+    fn label(&self) -> &'static str {
+        T::label(self)
+    }
 }
 
 #[cfg(test)]
@@ -20,4 +41,9 @@ mod tests {
     fn test_named_rc() {
         assert_eq!(Rc::<String>::name(), "String");
     }
+
+    #[test]
+    fn test_named_arc() {
+        assert_eq!(Arc::<String>::name(), "String");
+    }
 }
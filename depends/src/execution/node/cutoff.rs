@@ -0,0 +1,88 @@
+use std::{cell::Cell, hash::Hasher};
+
+use super::{NodeHash, NodeState};
+use crate::execution::HashValue;
+
+type Comparator<T> = Box<dyn Fn(&T, &T) -> bool>;
+
+/// Compares the value of a node before and after it is recalculated, so that
+/// a value which hasn't changed keeps its [NodeHash].
+pub(crate) struct Cutoff<T> {
+    /// Copies the value before it is recalculated.
+    snapshot: fn(&T) -> T,
+    /// Whether two values are equal.
+    eq: Comparator<T>,
+    /// The number of times a value which can't be hashed (or is verified) has
+    /// changed.
+    version: Cell<u64>,
+    /// If set, the hash of the value is only used as a fingerprint, and the
+    /// node is versioned instead. This holds the fingerprint of the previous
+    /// value.
+    fingerprint: Option<Cell<NodeHash>>,
+}
+
+impl<T: Clone> Cutoff<T> {
+    pub fn new(eq: impl Fn(&T, &T) -> bool + 'static) -> Self {
+        Self {
+            snapshot: T::clone,
+            eq: Box::new(eq),
+            version: Cell::new(0),
+            fingerprint: None,
+        }
+    }
+
+    /// A cutoff which can't be fooled by a hash collision. Values are only
+    /// considered unchanged if they are equal, and every change is given a new
+    /// version.
+    pub fn verified(eq: impl Fn(&T, &T) -> bool + 'static) -> Self {
+        Self {
+            fingerprint: Some(Cell::new(NodeHash::NotHashed)),
+            ..Self::new(eq)
+        }
+    }
+}
+
+impl<T: HashValue> Cutoff<T> {
+    pub fn snapshot(&self, value: &T) -> T {
+        (self.snapshot)(value)
+    }
+
+    /// Update the hash of `node_state`, unless its value is equal to
+    /// `previous`. Values which can't be hashed are given a new hash each
+    /// time they change, so they can still be compared by dependees.
+    pub fn update_node_hash(
+        &self,
+        node_state: &mut NodeState<T>,
+        previous: T,
+        hasher: &mut impl Hasher,
+    ) {
+        let hashed = !matches!(node_state.node_hash(), NodeHash::NotHashed);
+        if let Some(last) = &self.fingerprint {
+            let fingerprint = node_state.value().hash_value(hasher);
+            // Values with different fingerprints can't be equal, so they are
+            // only compared in full when the fingerprints match.
+            let maybe_equal = match (last.replace(fingerprint), fingerprint) {
+                (NodeHash::NotHashed, _) | (_, NodeHash::NotHashed) => true,
+                (last, fingerprint) => last == fingerprint,
+            };
+            if !(hashed && maybe_equal && (self.eq)(&previous, node_state.value())) {
+                self.bump(node_state);
+            }
+            return;
+        }
+        if hashed && (self.eq)(&previous, node_state.value()) {
+            return;
+        }
+        node_state.mark_changed();
+        node_state.update_node_hash(hasher);
+        if let NodeHash::NotHashed = node_state.node_hash() {
+            self.bump(node_state);
+        }
+    }
+
+    /// Give `node_state` a new version as its hash.
+    fn bump(&self, node_state: &mut NodeState<T>) {
+        self.version.set(self.version.get() + 1);
+        *node_state.node_hash_mut() = NodeHash::Hashed(self.version.get());
+    }
+}
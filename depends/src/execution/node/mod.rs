@@ -1,7 +1,15 @@
+mod cutoff;
 mod node_hash;
 pub mod node_ref;
 mod node_state;
+mod observers;
+mod staleness;
 
+pub(crate) use cutoff::Cutoff;
 pub use node_hash::NodeHash;
-pub use node_ref::NodeRef;
+pub use node_ref::{NodeRef, SyncNodeRef};
 pub use node_state::NodeState;
+pub use observers::NodeChange;
+pub(crate) use observers::{Observers, SyncObservers};
+pub(crate) use staleness::Dependees;
+pub use staleness::Staleness;
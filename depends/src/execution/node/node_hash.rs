@@ -1,3 +1,5 @@
+use std::hash::{Hash, Hasher};
+
 /// A Hash of the current node state used to signal whether a dependent node
 /// needs to update its internal state.
 ///
@@ -18,6 +20,13 @@
 /// that the number of nodes and edges _to_ nodes implementing `NotHashed` is
 /// kept to a minimum, especially where performance is a concern.
 ///
+/// # Hashed128
+///
+/// A collision between two 64-bit hashes would cause dependees to serve stale
+/// values. Where this is unacceptable, values can opt in to 128-bit hashes with
+/// [hashed_128](Self::hashed_128), or the `#[depends(hash_128)]` attribute. A
+/// `Hashed128` is never equal to a `Hashed`.
+///
 /// ```
 /// # use depends::NodeHash;
 /// // not equal
@@ -25,6 +34,7 @@
 /// assert_ne!(NodeHash::NotHashed, NodeHash::NotHashed);
 /// assert_ne!(NodeHash::NotHashed, NodeHash::Hashed(0));
 /// assert_ne!(NodeHash::Hashed(0), NodeHash::Hashed(1));
+/// assert_ne!(NodeHash::Hashed(0), NodeHash::Hashed128(0));
 /// // equal
 /// assert_eq!(NodeHash::Hashed(1), NodeHash::Hashed(1));
 /// ```
@@ -35,12 +45,27 @@ pub enum NodeHash {
     NotHashed,
     /// Equal to another value if the internal number is equal.
     Hashed(u64),
+    /// Equal to another value if the internal number is equal.
+    Hashed128(u128),
+}
+
+impl NodeHash {
+    /// Hash `value` to 128 bits, by hashing it twice with the same `hasher`.
+    /// As the state of the hasher carries over, the second half is not simply
+    /// a copy of the first.
+    pub fn hashed_128<T: Hash + ?Sized>(value: &T, hasher: &mut impl Hasher) -> Self {
+        value.hash(hasher);
+        let high = hasher.finish();
+        value.hash(hasher);
+        Self::Hashed128((u128::from(high) << 64) | u128::from(hasher.finish()))
+    }
 }
 
 impl PartialEq for NodeHash {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Hashed(a), Self::Hashed(b)) => a == b,
+            (Self::Hashed128(a), Self::Hashed128(b)) => a == b,
             _ => false,
         }
     }
@@ -56,4 +81,17 @@ mod tests {
         assert_eq!(hash.clone(), hash);
         assert_eq!("Hashed(420)", format!("{hash:?}"));
     }
+
+    #[test]
+    fn test_hashed_128() {
+        let hash = |value: &str| {
+            NodeHash::hashed_128(value, &mut std::collections::hash_map::DefaultHasher::new())
+        };
+        assert_eq!(hash("a"), hash("a"));
+        assert_ne!(hash("a"), hash("b"));
+        let NodeHash::Hashed128(wide) = hash("a") else {
+            panic!("expected a 128-bit hash");
+        };
+        assert_ne!(wide >> 64, wide & u128::from(u64::MAX));
+    }
 }
@@ -1,6 +1,10 @@
-use std::cell::Ref;
+use std::{cell::Ref, sync::RwLockReadGuard};
 
 use crate::NodeState;
 
 /// Short-hand for the output read-reference of a node.
 pub type NodeRef<'a, T> = Ref<'a, NodeState<T>>;
+
+/// Short-hand for the output read-reference of a thread-safe node, such as a
+/// [SyncInputNode](crate::SyncInputNode).
+pub type SyncNodeRef<'a, T> = RwLockReadGuard<'a, NodeState<T>>;
@@ -37,8 +37,27 @@ impl<T: HashValue> NodeState<T> {
     }
 
     /// Update the stored hash value of the value.
+    ///
+    /// [Versioned](HashValue::VERSIONED) values are not hashed, and keep their
+    /// current version.
     pub fn update_node_hash(&mut self, hasher: &mut impl Hasher) {
-        self.node_hash = self.value.hash_value(hasher)
+        if !T::VERSIONED {
+            self.node_hash = self.value.hash_value(hasher)
+        } else if let NodeHash::NotHashed = self.node_hash {
+            self.node_hash = NodeHash::Hashed(0)
+        }
+    }
+
+    /// Increment the version of a [versioned](HashValue::VERSIONED) value, so
+    /// that it appears changed to any dependents. This has no effect on
+    /// values which are hashed.
+    pub fn mark_changed(&mut self) {
+        if T::VERSIONED {
+            self.node_hash = match self.node_hash {
+                NodeHash::Hashed(version) => NodeHash::Hashed(version.wrapping_add(1)),
+                NodeHash::NotHashed | NodeHash::Hashed128(_) => NodeHash::Hashed(1),
+            }
+        }
     }
 
     pub fn value(&self) -> &T {
@@ -86,8 +105,8 @@ impl<T, D, F> UpdateDerived<D, F> for NodeState<T>
 where
     T: UpdateDerived<D, F>,
 {
-    fn update(&mut self, value: D) -> Result<(), EarlyExit> {
-        self.value.update(value)
+    fn update(&mut self, value: D, operation: &F) -> Result<(), EarlyExit> {
+        self.value.update(value, operation)
     }
 }
 
@@ -115,4 +134,31 @@ mod tests {
         *state.value_mut() = 456;
         assert_eq!(state.value(), &456);
     }
+
+    struct Versioned;
+
+    impl HashValue for Versioned {
+        const VERSIONED: bool = true;
+
+        fn hash_value(&self, _: &mut impl Hasher) -> NodeHash {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_versioned_node_state() {
+        let hasher = &mut std::collections::hash_map::DefaultHasher::new();
+        let mut state = NodeState::new(Versioned);
+        state.update_node_hash(hasher);
+        assert_eq!(state.node_hash(), NodeHash::Hashed(0));
+        state.mark_changed();
+        state.mark_changed();
+        state.update_node_hash(hasher);
+        assert_eq!(state.node_hash(), NodeHash::Hashed(2));
+
+        // Hashed values are unaffected.
+        let mut state = NodeState::new(123_i32);
+        state.mark_changed();
+        assert!(matches!(state.node_hash(), NodeHash::NotHashed));
+    }
 }
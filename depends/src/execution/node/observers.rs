@@ -0,0 +1,218 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    sync::{Mutex, MutexGuard},
+};
+
+use super::NodeHash;
+use crate::execution::Identifiable;
+
+/// A notification that the value of a node changed during a resolve, sent to
+/// any observers subscribed to that node.
+///
+/// A value which isn't hashed ([NodeHash::NotHashed]) can't be compared, so
+/// it's considered changed whenever it's recalculated, or resolved after an
+/// update.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NodeChange {
+    /// The node's [Identifiable::id].
+    pub id: usize,
+    /// The node's [Named::name](crate::Named::name).
+    pub name: &'static str,
+    /// For a [MultiDerivedNode](crate::MultiDerivedNode), the index of the
+    /// output which changed.
+    pub output: Option<usize>,
+    /// The hash of the node before it was recalculated.
+    pub previous: NodeHash,
+    /// The hash of the node after it was recalculated.
+    pub current: NodeHash,
+}
+
+impl NodeChange {
+    /// A change to `node`, if its hash has changed.
+    fn new<N: Identifiable>(
+        node: &N,
+        output: Option<usize>,
+        previous: NodeHash,
+        current: NodeHash,
+    ) -> Option<Self> {
+        (previous != current).then(|| {
+            Self {
+                id: node.id(),
+                name: node.label(),
+                output,
+                previous,
+                current,
+            }
+        })
+    }
+}
+
+type Observer = Box<dyn FnMut(&NodeChange)>;
+
+/// Callbacks to run when the value of a node changes.
+#[derive(Default)]
+pub(crate) struct Observers(RefCell<Vec<Observer>>);
+
+impl Observers {
+    pub fn subscribe(&self, observer: impl FnMut(&NodeChange) + 'static) {
+        self.0.borrow_mut().push(Box::new(observer));
+    }
+
+    /// Whether there are no observers to notify.
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    /// Notify every observer, if the hash of `node` has changed.
+    pub fn notify<N: Identifiable>(&self, node: &N, previous: NodeHash, current: NodeHash) {
+        self.notify_output(node, None, previous, current);
+    }
+
+    /// Notify every observer, if the hash of the output of `node` at index
+    /// `output` has changed.
+    pub fn notify_output<N: Identifiable>(
+        &self,
+        node: &N,
+        output: Option<usize>,
+        previous: NodeHash,
+        current: NodeHash,
+    ) {
+        let Some(change) = NodeChange::new(node, output, previous, current) else {
+            return;
+        };
+        // Observers may subscribe more observers whilst being notified.
+        let mut notifying = Notifying {
+            observers: self.0.take(),
+            restore: |observers| {
+                let mut subscribed = self.0.borrow_mut();
+                observers.append(&mut subscribed);
+                std::mem::swap(&mut *subscribed, observers);
+            },
+        };
+        for observer in notifying.observers.iter_mut() {
+            observer(&change);
+        }
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Observers")
+            .field("len", &self.0.borrow().len())
+            .finish()
+    }
+}
+
+type SyncObserver = Box<dyn FnMut(&NodeChange) + Send>;
+
+/// A thread-safe equivalent of [Observers].
+#[derive(Default)]
+pub(crate) struct SyncObservers {
+    observers: Mutex<Vec<SyncObserver>>,
+    /// Held whilst notifying, so that no notification is missed by a thread
+    /// which finds the observers taken by another.
+    notifying: Mutex<()>,
+}
+
+impl SyncObservers {
+    pub fn subscribe(&self, observer: impl FnMut(&NodeChange) + Send + 'static) {
+        lock(&self.observers).push(Box::new(observer));
+    }
+
+    /// Notify every observer, if the hash of `node` has changed.
+    ///
+    /// An observer which causes this node to notify again, on the same
+    /// thread, will deadlock.
+    pub fn notify<N: Identifiable>(&self, node: &N, previous: NodeHash, current: NodeHash) {
+        let Some(change) = NodeChange::new(node, None, previous, current) else {
+            return;
+        };
+        let _notifying = lock(&self.notifying);
+        // Observers may subscribe more observers whilst being notified.
+        let mut notifying = Notifying {
+            observers: std::mem::take(&mut *lock(&self.observers)),
+            restore: |observers| {
+                let mut subscribed = lock(&self.observers);
+                observers.append(&mut subscribed);
+                std::mem::swap(&mut *subscribed, observers);
+            },
+        };
+        for observer in notifying.observers.iter_mut() {
+            observer(&change);
+        }
+    }
+}
+
+impl fmt::Debug for SyncObservers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncObservers")
+            .field("len", &lock(&self.observers).len())
+            .finish()
+    }
+}
+
+/// Observers taken whilst they're notified. They're returned once dropped,
+/// including when an observer panics, so that none are lost.
+struct Notifying<O, R: FnMut(&mut Vec<O>)> {
+    observers: Vec<O>,
+    restore: R,
+}
+
+impl<O, R: FnMut(&mut Vec<O>)> Drop for Notifying<O, R> {
+    fn drop(&mut self) {
+        (self.restore)(&mut self.observers);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::{
+        panic::{self, AssertUnwindSafe},
+        rc::Rc,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::execution::{internal_test_utils::TestData, InputNode, NodeIds};
+
+    #[test]
+    fn test_observer_panic() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let node = InputNode::new(TestData::new(0));
+        let observers = Observers::default();
+        let calls = Rc::new(RefCell::new(0));
+        observers.subscribe({
+            let calls = Rc::clone(&calls);
+            move |_| *calls.borrow_mut() += 1
+        });
+        observers.subscribe(|_| panic!("observer failed"));
+
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            observers.notify(node.as_ref(), NodeHash::Hashed(0), NodeHash::Hashed(1));
+        }));
+        assert!(res.is_err());
+        // Every observer is still subscribed.
+        assert_eq!(format!("{observers:?}"), "Observers { len: 2 }");
+        assert_eq!(*calls.borrow(), 1);
+
+        let sync_observers = SyncObservers::default();
+        let calls = Arc::new(Mutex::new(0));
+        sync_observers.subscribe({
+            let calls = Arc::clone(&calls);
+            move |_| *calls.lock().unwrap() += 1
+        });
+        sync_observers.subscribe(|_| panic!("observer failed"));
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            sync_observers.notify(node.as_ref(), NodeHash::Hashed(0), NodeHash::Hashed(1));
+        }));
+        assert!(res.is_err());
+        assert_eq!(format!("{sync_observers:?}"), "SyncObservers { len: 2 }");
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    rc::{Rc, Weak},
+};
+
+/// Whether a node may have changed since it was last resolved.
+///
+/// When an [InputNode](crate::InputNode) is updated, every node which
+/// (transitively) depends on it is marked stale. A node which isn't stale
+/// can return its cached value without resolving any of its dependencies.
+///
+/// Marking stops at nodes which are already stale, as their dependees must
+/// be too. This keeps the cost of an update proportional to the number of
+/// nodes it newly affects.
+pub struct Staleness {
+    stale: Cell<bool>,
+    dependees: Dependees,
+}
+
+impl Staleness {
+    /// A node is stale until it's first resolved.
+    pub(crate) fn new() -> Rc<Self> {
+        Rc::new(Self {
+            stale: Cell::new(true),
+            dependees: Dependees::default(),
+        })
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.stale.get()
+    }
+
+    /// Mark this node, and each of its dependees, stale.
+    pub fn mark(&self) {
+        if !self.stale.replace(true) {
+            self.dependees.mark();
+        }
+    }
+
+    /// Start resolving this node, returning whether it was stale.
+    pub(crate) fn take(&self) -> bool {
+        self.stale.replace(false)
+    }
+
+    /// Mark `dependee` stale whenever this node is.
+    pub(crate) fn subscribe(&self, dependee: &Rc<Staleness>) {
+        self.dependees.subscribe(dependee);
+    }
+}
+
+impl fmt::Debug for Staleness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Staleness")
+            .field("stale", &self.stale.get())
+            .field("dependees", &self.dependees)
+            .finish()
+    }
+}
+
+/// The [Staleness] of each node which depends on a node.
+#[derive(Default)]
+pub(crate) struct Dependees(RefCell<Vec<Weak<Staleness>>>);
+
+impl Dependees {
+    /// Subscribe `dependee`, unless it already is. Dependees subscribe again
+    /// whenever their dependencies are changed.
+    pub fn subscribe(&self, dependee: &Rc<Staleness>) {
+        let dependee = Rc::downgrade(dependee);
+        let mut dependees = self.0.borrow_mut();
+        if !dependees.iter().any(|d| d.ptr_eq(&dependee)) {
+            dependees.push(dependee);
+        }
+    }
+
+    /// Mark each dependee stale, forgetting any which have been dropped.
+    pub fn mark(&self) {
+        // Dependees are never marked whilst being subscribed, and marking
+        // stops at a node which is already stale, so this can't be
+        // re-entered.
+        self.0
+            .borrow_mut()
+            .retain(|dependee| dependee.upgrade().map(|dependee| dependee.mark()).is_some());
+    }
+}
+
+impl fmt::Debug for Dependees {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dependees")
+            .field("len", &self.0.borrow().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staleness() {
+        let input = Dependees::default();
+        let a = Staleness::new();
+        let b = Staleness::new();
+        let c = Staleness::new();
+        input.subscribe(&a);
+        a.subscribe(&b);
+        b.subscribe(&c);
+        assert!(a.take() && b.take() && c.take());
+
+        input.mark();
+        assert!(a.is_stale() && b.is_stale() && c.is_stale());
+
+        // Marking stops at `b`, which is already stale.
+        assert!(a.take() && c.take());
+        input.mark();
+        assert!(a.is_stale() && b.is_stale() && !c.is_stale());
+
+        // Dependees are only subscribed once, and are forgotten once dropped.
+        input.subscribe(&a);
+        assert_eq!(format!("{input:?}"), "Dependees { len: 1 }");
+        drop(a);
+        input.mark();
+        assert_eq!(format!("{input:?}"), "Dependees { len: 0 }");
+    }
+}
@@ -25,6 +25,8 @@ macro_rules! impl_for_primitive {
                 fn clean(&mut self) {}
             }
 
+            impl crate::execution::UpdateFn for $ty {}
+
             impl crate::execution::UpdateInput for $ty {
                 type Update = Self;
 
@@ -1,6 +1,6 @@
-use std::rc::Rc;
+use std::{rc::Rc, sync::Arc};
 
-use crate::execution::{error::ResolveResult, Visitor};
+use crate::execution::{error::ResolveResult, Staleness, Visitor};
 
 /// A [Depth-first search](https://en.wikipedia.org/wiki/Depth-first_search) resolver, used to
 /// recursively pass a [Visitor] through a graph, updating dependencies.
@@ -23,6 +23,25 @@ pub trait Resolve {
         visitor.clear();
         res
     }
+
+    /// Mark `dependee` [stale](Staleness) whenever the output of this node
+    /// may have changed. Returns `false` if this node can't track its
+    /// changes, in which case `dependee` must resolve it every time. This is
+    /// the default.
+    ///
+    /// > [Staleness] isn't thread-safe, so thread-safe nodes such as a
+    /// > [SyncInputNode](crate::SyncInputNode) or
+    /// > [SyncDerivedNode](crate::SyncDerivedNode) use the default.
+    fn subscribe_stale(&self, _dependee: &Rc<Staleness>) -> bool {
+        false
+    }
+
+    /// Called on the dependencies of a node after they were resolved, if
+    /// they, or the node's operation, then failed. State which is only
+    /// reported once, such as the [removed](crate::DependencyEdges::removed)
+    /// ids of a [DependencyVec](crate::DependencyVec), must be reported again
+    /// by the next resolve. The default does nothing.
+    fn rollback(&self) {}
 }
 
 impl<T: Resolve> Resolve for Rc<T> {
@@ -34,4 +53,31 @@ impl<T: Resolve> Resolve for Rc<T> {
     fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
         T::resolve(self, visitor)
     }
+
+    fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        T::subscribe_stale(self, dependee)
+    }
+
+    fn rollback(&self) {
+        T::rollback(self)
+    }
+}
+
+impl<T: Resolve> Resolve for Arc<T> {
+    type Output<'a>
+        = T::Output<'a>
+    where
+        Self: 'a;
+
+    fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        T::resolve(self, visitor)
+    }
+
+    fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        T::subscribe_stale(self, dependee)
+    }
+
+    fn rollback(&self) {
+        T::rollback(self)
+    }
 }
@@ -0,0 +1,66 @@
+use std::{future::Future, rc::Rc};
+
+use crate::execution::{error::ResolveResult, Visitor};
+
+/// An asynchronous equivalent of [Resolve](super::Resolve), for graphs
+/// containing nodes whose operations must await, such as an
+/// [AsyncDerivedNode](super::AsyncDerivedNode).
+///
+/// Input and derived nodes also implement this trait, resolving
+/// synchronously, so that they can be used as dependencies of asynchronous
+/// nodes.
+///
+/// > Nodes remain borrowed whilst their operation is pending. The returned
+/// > futures are not `Send`, and are intended to be driven by a local
+/// > executor.
+pub trait AsyncResolve {
+    type Output<'a>
+    where
+        Self: 'a;
+
+    /// You're probably looking for
+    /// [resolve_root_async](Self::resolve_root_async). This is recursively
+    /// called on each node when a graph is being resolved.
+    ///
+    /// Pass a [Visitor] through this node, resolve the latest version of all
+    /// dependencies and return this node's output.
+    fn resolve_async(
+        &self,
+        visitor: &mut impl Visitor,
+    ) -> impl Future<Output = ResolveResult<Self::Output<'_>>>;
+
+    /// Pass a [Visitor] through this node, resolve the latest version of all
+    /// dependencies, reset the visitor and return this node's output.
+    fn resolve_root_async(
+        &self,
+        visitor: &mut impl Visitor,
+    ) -> impl Future<Output = ResolveResult<Self::Output<'_>>> {
+        async move {
+            let res = self.resolve_async(visitor).await;
+            visitor.clear();
+            res
+        }
+    }
+
+    /// The equivalent of [Resolve::rollback](super::Resolve::rollback), called
+    /// after a failed asynchronous resolve.
+    fn rollback(&self) {}
+}
+
+impl<T: AsyncResolve> AsyncResolve for Rc<T> {
+    type Output<'a>
+        = T::Output<'a>
+    where
+        Self: 'a;
+
+    fn resolve_async(
+        &self,
+        visitor: &mut impl Visitor,
+    ) -> impl Future<Output = ResolveResult<Self::Output<'_>>> {
+        T::resolve_async(self, visitor)
+    }
+
+    fn rollback(&self) {
+        T::rollback(self)
+    }
+}
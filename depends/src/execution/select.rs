@@ -0,0 +1,259 @@
+use std::{
+    cell::{Cell, Ref},
+    hash::Hasher,
+    ops::Deref,
+    rc::Rc,
+};
+
+use crate::execution::{
+    error::{trace_node, ResolveResult},
+    next_node_id, DependencyEdge, HashValue, Identifiable, Named, NodeHash, NodeRef, Resolve,
+    Staleness, Visitor,
+};
+
+/// Short-hand for a reference to a single [Select] dependency.
+pub type DepSelect<'a, P> = DependencyEdge<'a, Selected<'a, P>>;
+
+/// # Select
+///
+/// A node which projects a part of the value of another node, and is hashed
+/// by that part alone. Dependees of a `Select` are only dirty when the
+/// selected part changes, even if the rest of the upstream value has.
+///
+/// Nothing is copied: the projection is a reference in to the upstream node.
+/// The selected part is only re-hashed when the upstream node has changed.
+///
+/// ```
+/// # use std::rc::Rc;
+/// # use depends::{DepSelect, Dependency, DerivedNode, InputNode, Resolve, Select, UpdateDerived, UpdateInput};
+/// # use depends::derives::{Operation, Value};
+/// # use depends::error::EarlyExit;
+/// #[derive(Value, Hash)]
+/// struct Order {
+///     price: i64,
+///     quantity: i64,
+/// }
+/// # impl UpdateInput for Order {
+/// #     type Update = (i64, i64);
+/// #     fn update_mut(&mut self, (price, quantity): Self::Update) {
+/// #         self.price = price;
+/// #         self.quantity = quantity;
+/// #     }
+/// # }
+///
+/// #[derive(Operation)]
+/// struct Double;
+///
+/// impl UpdateDerived<DepSelect<'_, i64>, Double> for i64 {
+///     fn update(&mut self, deps: DepSelect<'_, i64>, _: &Double) -> Result<(), EarlyExit> {
+///         *self = **deps * 2;
+///         Ok(())
+///     }
+/// }
+///
+/// let order = InputNode::new(Order { price: 100, quantity: 1 });
+/// let price = Select::new(Rc::clone(&order), |order: &Order| &order.price);
+/// let doubled = DerivedNode::new(Dependency::new(price), Double, 0);
+///
+/// let mut visitor = std::collections::HashSet::<usize>::new();
+/// assert_eq!(*doubled.resolve_root(&mut visitor).unwrap().value(), 200);
+///
+/// // `doubled` is not dirtied, as the price hasn't changed.
+/// order.update((100, 5)).unwrap();
+/// assert_eq!(*doubled.resolve_root(&mut visitor).unwrap().value(), 200);
+/// ```
+pub struct Select<N, F> {
+    /// The node to project.
+    node: N,
+    /// Projects the value of `node`.
+    select: F,
+    /// The hash of `node` when the selected part was last hashed, along with
+    /// that hash.
+    last_hash: Cell<Option<(NodeHash, NodeHash)>>,
+    /// The unique runtime Id of this node.
+    id: usize,
+}
+
+impl<N, F> Select<N, F> {
+    /// Construct this node.
+    pub fn new<T, P>(node: N, select: F) -> Rc<Self>
+    where
+        N: for<'a> Resolve<Output<'a> = NodeRef<'a, T>> + 'static,
+        T: HashValue + 'static,
+        F: Fn(&T) -> &P,
+        P: HashValue + 'static,
+    {
+        Rc::new(Self {
+            node,
+            select,
+            last_hash: Cell::new(None),
+            id: next_node_id(),
+        })
+    }
+}
+
+impl<N, F, T, P> Resolve for Select<N, F>
+where
+    N: for<'a> Resolve<Output<'a> = NodeRef<'a, T>> + 'static,
+    T: HashValue + 'static,
+    F: Fn(&T) -> &P,
+    P: HashValue + 'static,
+{
+    type Output<'a>
+        = Selected<'a, P>
+    where
+        Self: 'a;
+
+    fn resolve(&self, visitor: &mut impl Visitor) -> ResolveResult<Self::Output<'_>> {
+        trace_node(self, Some(Self::name()), || {
+            visitor.touch(self, Some(Self::name()));
+            let value = self.node.resolve(visitor)?;
+            let upstream = value.node_hash();
+            let node_hash = match self.last_hash.get() {
+                Some((last, node_hash)) if last == upstream => node_hash,
+                _ => {
+                    let node_hash = (self.select)(&value).hash_value(&mut visitor.hasher());
+                    self.last_hash.set(Some((upstream, node_hash)));
+                    node_hash
+                }
+            };
+            visitor.leave(self);
+            Ok(Selected {
+                value: Ref::map(value, |value| (self.select)(value)),
+                node_hash,
+            })
+        })
+    }
+
+    fn subscribe_stale(&self, dependee: &Rc<Staleness>) -> bool {
+        self.node.subscribe_stale(dependee)
+    }
+}
+
+impl<N, F> Named for Select<N, F> {
+    fn name() -> &'static str {
+        "Select"
+    }
+}
+
+impl<N, F> Identifiable for Select<N, F> {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+/// A reference to the part of a value projected by a [Select].
+pub struct Selected<'a, P> {
+    value: Ref<'a, P>,
+    node_hash: NodeHash,
+}
+
+impl<P> Deref for Selected<'_, P> {
+    type Target = P;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<P> HashValue for Selected<'_, P> {
+    fn hash_value(&self, _: &mut impl Hasher) -> NodeHash {
+        self.node_hash
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::hash::Hash;
+
+    use super::*;
+    use crate::{
+        error::EarlyExit,
+        execution::{DiagnosticVisitor, NodeIds},
+        Clean, Dependency, DerivedNode, InputNode, UpdateDerived, UpdateInput,
+    };
+
+    struct Pair {
+        left: u32,
+        right: u32,
+    }
+
+    impl Named for Pair {
+        fn name() -> &'static str {
+            "Pair"
+        }
+    }
+
+    impl HashValue for Pair {
+        fn hash_value(&self, hasher: &mut impl Hasher) -> NodeHash {
+            (self.left, self.right).hash(hasher);
+            NodeHash::Hashed(hasher.finish())
+        }
+    }
+
+    impl Clean for Pair {
+        fn clean(&mut self) {}
+    }
+
+    impl UpdateInput for Pair {
+        type Update = (u32, u32);
+
+        fn update_mut(&mut self, (left, right): Self::Update) {
+            self.left = left;
+            self.right = right;
+        }
+    }
+
+    struct Square;
+
+    impl Named for Square {
+        fn name() -> &'static str {
+            "Square"
+        }
+    }
+
+    impl UpdateDerived<DepSelect<'_, u32>, Square> for u32 {
+        fn update(&mut self, deps: DepSelect<'_, u32>, _: &Square) -> Result<(), EarlyExit> {
+            *self = deps.pow(2);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let pair = InputNode::new(Pair { left: 2, right: 3 });
+        let hashed = Rc::new(Cell::new(0));
+        let left = Select::new(Rc::clone(&pair), {
+            let hashed = Rc::clone(&hashed);
+            move |pair: &Pair| {
+                hashed.set(hashed.get() + 1);
+                &pair.left
+            }
+        });
+        let square = DerivedNode::new(Dependency::new(Rc::clone(&left)), Square, 0_u32);
+        let mut visitor = DiagnosticVisitor::new();
+
+        assert_eq!(*square.resolve(&mut visitor).unwrap().value(), 4);
+        assert_eq!(visitor.recalculated, [2].into());
+        visitor.clear();
+
+        // Changes to the rest of the value are cut off.
+        pair.update((2, 4)).unwrap();
+        assert_eq!(*square.resolve(&mut visitor).unwrap().value(), 4);
+        assert!(visitor.recalculated.is_empty());
+        visitor.clear();
+
+        pair.update((5, 4)).unwrap();
+        assert_eq!(*square.resolve(&mut visitor).unwrap().value(), 25);
+        assert_eq!(visitor.recalculated, [2].into());
+        visitor.clear();
+
+        // The upstream node is unchanged, so the projection is only called
+        // to borrow the selected part, and not to hash it again.
+        let projected = hashed.get();
+        assert_eq!(*left.resolve(&mut visitor).unwrap(), 5);
+        assert_eq!(hashed.get(), projected + 1);
+    }
+}
@@ -2,4 +2,5 @@
 //!
 //! This module contains utilities for testing and debugging resolution
 //! behaviour.
+#[allow(deprecated)]
 pub use super::identifiable::ext_reset_node_id;
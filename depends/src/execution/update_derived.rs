@@ -1,5 +1,8 @@
 use crate::execution::error::EarlyExit;
 
 pub trait UpdateDerived<T, F> {
-    fn update(&mut self, deps: T) -> Result<(), EarlyExit>;
+    /// Update the target from its dependencies. `operation` is the value
+    /// the node was constructed with, so can be used to pass parameters to
+    /// the operation.
+    fn update(&mut self, deps: T, operation: &F) -> Result<(), EarlyExit>;
 }
@@ -0,0 +1,9 @@
+use std::future::Future;
+
+use crate::execution::error::EarlyExit;
+
+/// An asynchronous equivalent of [UpdateDerived](super::UpdateDerived), used
+/// by an [AsyncDerivedNode](super::AsyncDerivedNode) to await its operation.
+pub trait AsyncUpdateDerived<T, F> {
+    fn update(&mut self, deps: T, operation: &F) -> impl Future<Output = Result<(), EarlyExit>>;
+}
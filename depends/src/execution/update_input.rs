@@ -1,3 +1,5 @@
+use std::error::Error;
+
 use super::{Clean, HashValue};
 use crate::execution::Named;
 
@@ -95,3 +97,67 @@ pub trait UpdateInput: Named + HashValue + Clean {
 
     fn update_mut(&mut self, update: Self::Update);
 }
+
+/// An [UpdateInput] which can reject an update, via
+/// [InputNode::try_update](super::InputNode::try_update).
+///
+/// Updates are checked before the node is changed in any way, so a rejected
+/// update leaves both the value and its [InputState](super::InputState)
+/// untouched.
+///
+/// ```
+/// # use depends::{InputNode, TryUpdateInput, UpdateInput};
+/// # use depends::derives::Value;
+/// # use depends::error::ResolveError;
+/// #[derive(Debug, PartialEq)]
+/// struct Negative(i32);
+///
+/// impl std::fmt::Display for Negative {
+///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+///         write!(f, "{} is negative", self.0)
+///     }
+/// }
+///
+/// impl std::error::Error for Negative {}
+///
+/// #[derive(Value, Hash)]
+/// struct Quantity {
+///     value: i32,
+/// }
+///
+/// impl UpdateInput for Quantity {
+///     type Update = i32;
+///
+///     fn update_mut(&mut self, update: Self::Update) {
+///         self.value = update;
+///     }
+/// }
+///
+/// impl TryUpdateInput for Quantity {
+///     type Error = Negative;
+///
+///     fn validate_update(&self, update: &Self::Update) -> Result<(), Self::Error> {
+///         if *update < 0 {
+///             Err(Negative(*update))
+///         } else {
+///             Ok(())
+///         }
+///     }
+/// }
+///
+/// let input = InputNode::new(Quantity { value: 1 });
+/// input.try_update(2).unwrap();
+///
+/// let Err(ResolveError::Rejected(err)) = input.try_update(-1) else {
+///     panic!("expected the update to be rejected");
+/// };
+/// assert_eq!(err.downcast_ref::<Negative>(), Some(&Negative(-1)));
+/// assert_eq!(input.value().unwrap().value, 2);
+/// ```
+pub trait TryUpdateInput: UpdateInput {
+    type Error: Error + Send + Sync + 'static;
+
+    /// Check whether `update` can be applied with
+    /// [update_mut](UpdateInput::update_mut).
+    fn validate_update(&self, update: &Self::Update) -> Result<(), Self::Error>;
+}
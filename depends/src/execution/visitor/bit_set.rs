@@ -0,0 +1,167 @@
+use std::{
+    collections::{hash_map::RandomState, HashSet},
+    hash::BuildHasher,
+};
+
+use super::{Visitor, DENSE_IDS};
+use crate::execution::Identifiable;
+
+/// The number of node Ids tracked by each word.
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// # Bit Set Visitor
+///
+/// A [Visitor] which tracks visited nodes with a single bit per node Id,
+/// rather than hashing each Id in to a set. Visiting a node is a single bit
+/// test-and-set, and clearing only resets the words which were touched since
+/// the last clear.
+///
+/// Nodes must have been created in a [NodeIds](crate::NodeIds) scope, such as
+/// those allocated in an [Arena](crate::Arena), as the Ids are dense and start
+/// at zero. Memory is allocated up to the largest Id visited. Nodes created
+/// outside of any scope, or with Ids too large to index, are tracked in a
+/// [HashSet] instead.
+///
+/// > Ids are only unique within a scope, so nodes of two graphs created in
+/// > different scopes collide, and one would be skipped as already visited.
+/// > Use a separate visitor for each graph.
+///
+/// Edges are hashed with `S`, which is [RandomState] by default.
+#[derive(Debug, Clone, Default)]
+pub struct BitSetVisitor<S = RandomState> {
+    /// Bit `i % 64` of word `i / 64` is set if node `i` has been visited.
+    words: Vec<u64>,
+    /// The index of each word with a bit set since the last clear.
+    touched: Vec<usize>,
+    /// The visited nodes whose Ids are too large to index.
+    sparse: HashSet<usize>,
+    /// Builds the hasher of each resolve, so that hashes are consistent for
+    /// the lifetime of this visitor.
+    state: S,
+}
+
+impl BitSetVisitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a visitor with space for node Ids up to `capacity`.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, RandomState::new())
+    }
+}
+
+impl<S> BitSetVisitor<S> {
+    /// Create a visitor with space for node Ids up to `capacity`, which
+    /// hashes edges with `state`.
+    pub fn with_capacity_and_hasher(capacity: usize, state: S) -> Self {
+        Self {
+            words: vec![0; capacity.div_ceil(WORD_BITS)],
+            touched: Vec::new(),
+            sparse: HashSet::new(),
+            state,
+        }
+    }
+
+    /// Whether the node with this Id has been visited.
+    pub fn contains(&self, id: usize) -> bool {
+        if id >= DENSE_IDS {
+            return self.sparse.contains(&id);
+        }
+        self.words
+            .get(id / WORD_BITS)
+            .is_some_and(|word| word & (1 << (id % WORD_BITS)) != 0)
+    }
+}
+
+impl<S: BuildHasher> Visitor for BitSetVisitor<S> {
+    type Hasher = S::Hasher;
+
+    fn visit<N>(&mut self, node: &N) -> bool
+    where
+        N: Identifiable,
+    {
+        let id = node.id();
+        if id >= DENSE_IDS {
+            return self.sparse.insert(id);
+        }
+        let index = id / WORD_BITS;
+        if index >= self.words.len() {
+            self.words.resize(index + 1, 0);
+        }
+        let mask = 1 << (id % WORD_BITS);
+        let word = &mut self.words[index];
+        if *word & mask != 0 {
+            return false;
+        }
+        if *word == 0 {
+            self.touched.push(index);
+        }
+        *word |= mask;
+        true
+    }
+
+    fn clear(&mut self) {
+        for index in self.touched.drain(..) {
+            self.words[index] = 0;
+        }
+        self.sparse.clear();
+    }
+
+    fn hasher(&self) -> Self::Hasher {
+        self.state.build_hasher()
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::hash::Hasher;
+
+    use super::*;
+    use crate::{
+        execution::{internal_test_utils::TestData, NodeIds},
+        InputNode,
+    };
+
+    #[test]
+    fn test_bit_set_visitor() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let nodes = (0..300)
+            .map(|i| InputNode::new(TestData::new(i)))
+            .collect::<Vec<_>>();
+        let mut visitor = BitSetVisitor::with_capacity(64);
+        assert!(visitor.visit(&nodes[0]));
+        assert!(!visitor.visit(&nodes[0]));
+        assert!(visitor.visit(&nodes[1]));
+        assert!(visitor.visit(&nodes[129]));
+        assert!(!visitor.visit(&nodes[129]));
+        assert!(visitor.contains(0) && visitor.contains(129));
+        assert!(!visitor.contains(2) && !visitor.contains(1000));
+        // Only the words which were written to are cleared.
+        assert_eq!(visitor.touched, [0, 2]);
+        visitor.clear();
+        assert!(visitor.touched.is_empty());
+        assert!(visitor.words.iter().all(|word| *word == 0));
+        assert!(visitor.visit(&nodes[129]));
+        let hash = |mut hasher: <BitSetVisitor as Visitor>::Hasher| {
+            hasher.write_usize(654);
+            hasher.finish()
+        };
+        assert_eq!(hash(visitor.hasher()), hash(visitor.hasher()));
+    }
+
+    #[test]
+    fn test_bit_set_visitor_sparse() {
+        let node = InputNode::new_with_id(TestData::new(0), usize::MAX);
+        let mut visitor = BitSetVisitor::new();
+        assert!(visitor.visit(&node));
+        assert!(!visitor.visit(&node));
+        assert!(visitor.contains(usize::MAX));
+        // No memory is allocated for the Id.
+        assert!(visitor.words.is_empty());
+        visitor.clear();
+        assert!(!visitor.contains(usize::MAX));
+        assert!(visitor.visit(&node));
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    collections::{hash_map::RandomState, HashSet},
+    hash::BuildHasher,
+};
+
+use super::{Visitor, DENSE_IDS};
+use crate::execution::Identifiable;
+
+/// # Epoch Visitor
+///
+/// A [Visitor] which records the epoch each node was last visited in, rather
+/// than the set of visited nodes. A node has been visited if its epoch is the
+/// current one, so clearing is just starting a new epoch. This makes
+/// repeatedly resolving a large graph, where only a few nodes change each
+/// time, independent of how many nodes were visited.
+///
+/// Like the [BitSetVisitor](super::BitSetVisitor), epochs are indexed by node
+/// Id, so nodes must have been created in a [NodeIds](crate::NodeIds) scope,
+/// and those of different scopes must not be resolved with the same visitor.
+/// Memory is allocated up to the largest Id visited. Nodes created outside of
+/// any scope, or with Ids too large to index, are tracked in a [HashSet]
+/// which is emptied by each clear.
+///
+/// Edges are hashed with `S`, which is [RandomState] by default.
+///
+/// ```
+/// # use depends::{EpochVisitor, InputNode, NodeIds, Resolve};
+/// let ids = NodeIds::new();
+/// let node = ids.scope(|| InputNode::new(1_i32));
+///
+/// let mut visitor = EpochVisitor::new();
+/// for i in 2..10 {
+///     node.update(i).unwrap();
+///     // Each call to `clear` starts a new epoch.
+///     assert_eq!(*node.resolve_root(&mut visitor).unwrap().value(), i);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct EpochVisitor<S = RandomState> {
+    /// The epoch each node was last visited in, indexed by node Id. Zero if
+    /// it hasn't been visited since the epochs last wrapped around.
+    epochs: Vec<u32>,
+    /// The current epoch, which is never zero.
+    epoch: u32,
+    /// The nodes visited in the current epoch whose Ids are too large to
+    /// index.
+    sparse: HashSet<usize>,
+    /// Builds the hasher of each resolve, so that hashes are consistent for
+    /// the lifetime of this visitor.
+    state: S,
+}
+
+impl EpochVisitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a visitor with space for node Ids up to `capacity`.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, RandomState::new())
+    }
+}
+
+impl<S: Default> Default for EpochVisitor<S> {
+    fn default() -> Self {
+        Self::with_capacity_and_hasher(0, S::default())
+    }
+}
+
+impl<S> EpochVisitor<S> {
+    /// Create a visitor with space for node Ids up to `capacity`, which
+    /// hashes edges with `state`.
+    pub fn with_capacity_and_hasher(capacity: usize, state: S) -> Self {
+        Self {
+            epochs: vec![0; capacity],
+            epoch: 1,
+            sparse: HashSet::new(),
+            state,
+        }
+    }
+
+    /// Whether the node with this Id has been visited in the current epoch.
+    pub fn contains(&self, id: usize) -> bool {
+        if id >= DENSE_IDS {
+            return self.sparse.contains(&id);
+        }
+        self.epochs.get(id) == Some(&self.epoch)
+    }
+}
+
+impl<S: BuildHasher> Visitor for EpochVisitor<S> {
+    type Hasher = S::Hasher;
+
+    fn visit<N>(&mut self, node: &N) -> bool
+    where
+        N: Identifiable,
+    {
+        let id = node.id();
+        if id >= DENSE_IDS {
+            return self.sparse.insert(id);
+        }
+        if id >= self.epochs.len() {
+            self.epochs.resize(id + 1, 0);
+        }
+        let epoch = &mut self.epochs[id];
+        if *epoch == self.epoch {
+            return false;
+        }
+        *epoch = self.epoch;
+        true
+    }
+
+    fn clear(&mut self) {
+        self.sparse.clear();
+        match self.epoch.checked_add(1) {
+            Some(epoch) => self.epoch = epoch,
+            // Once every `u32::MAX` clears, old epochs could be mistaken for
+            // new ones, so they're forgotten.
+            None => {
+                self.epochs.fill(0);
+                self.epoch = 1;
+            }
+        }
+    }
+
+    fn hasher(&self) -> Self::Hasher {
+        self.state.build_hasher()
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use super::*;
+    use crate::{
+        execution::{internal_test_utils::TestData, NodeIds},
+        InputNode,
+    };
+
+    #[test]
+    fn test_epoch_visitor() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let nodes = (0..100)
+            .map(|i| InputNode::new(TestData::new(i)))
+            .collect::<Vec<_>>();
+        let mut visitor = EpochVisitor::with_capacity(10);
+        assert!(visitor.visit(&nodes[0]));
+        assert!(!visitor.visit(&nodes[0]));
+        assert!(visitor.visit(&nodes[99]));
+        assert!(visitor.contains(0) && visitor.contains(99));
+        assert!(!visitor.contains(1) && !visitor.contains(1000));
+
+        // Clearing only starts a new epoch.
+        visitor.clear();
+        assert_eq!(visitor.epochs[99], 1);
+        assert!(!visitor.contains(0) && !visitor.contains(99));
+        assert!(visitor.visit(&nodes[99]));
+        assert!(!visitor.visit(&nodes[99]));
+        assert_eq!(visitor.epochs[99], 2);
+    }
+
+    #[test]
+    fn test_epoch_visitor_wraps() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let nodes = (0..2)
+            .map(|i| InputNode::new(TestData::new(i)))
+            .collect::<Vec<_>>();
+        let mut visitor = EpochVisitor::new();
+        visitor.epoch = u32::MAX - 1;
+        assert!(visitor.visit(&nodes[0]));
+        visitor.clear();
+        assert!(visitor.visit(&nodes[1]));
+        visitor.clear();
+        // Every epoch recorded before wrapping around is forgotten.
+        assert_eq!(visitor.epoch, 1);
+        assert!(!visitor.contains(0) && !visitor.contains(1));
+        assert!(visitor.visit(&nodes[0]));
+    }
+
+    #[test]
+    fn test_epoch_visitor_sparse() {
+        let node = InputNode::new_with_id(TestData::new(0), usize::MAX);
+        let mut visitor = EpochVisitor::new();
+        assert!(visitor.visit(&node));
+        assert!(!visitor.visit(&node));
+        assert!(visitor.contains(usize::MAX));
+        // No memory is allocated for the Id.
+        assert!(visitor.epochs.is_empty());
+        visitor.clear();
+        assert!(!visitor.contains(usize::MAX));
+        assert!(visitor.visit(&node));
+    }
+}
@@ -0,0 +1,159 @@
+use std::hash::Hasher;
+
+use super::{ParallelVisitor, Visitor};
+use crate::execution::{error::NodeFrame, Identifiable, Named};
+
+/// An object-safe version of [Visitor], implemented for every visitor.
+///
+/// Nodes are only known by their id and [label](Named::label), so visitors
+/// which rely on [Named::name] will see the name of [ErasedNode] instead.
+trait VisitorObject<'a> {
+    fn visit(&mut self, id: usize, label: &'static str) -> bool;
+
+    fn clear(&mut self);
+
+    fn touch(&mut self, id: usize, label: &'static str, operation: Option<&'static str>);
+
+    fn notify_recalculated(&mut self, id: usize, label: &'static str);
+
+    fn touch_dependency_group(&mut self, dep: &'static str);
+
+    fn leave(&mut self, id: usize, label: &'static str);
+
+    fn hasher(&self) -> Box<dyn Hasher + 'a>;
+
+    fn fork(&self) -> Option<ParallelVisitor>;
+
+    fn take_cycle(&mut self) -> Option<Vec<NodeFrame>>;
+
+    fn skip_fresh(&self) -> bool;
+}
+
+/// Stands in for a node when passed to a [VisitorObject].
+struct ErasedNode {
+    id: usize,
+    label: &'static str,
+}
+
+impl Named for ErasedNode {
+    fn name() -> &'static str {
+        "ErasedNode"
+    }
+
+    fn label(&self) -> &'static str {
+        self.label
+    }
+}
+
+impl Identifiable for ErasedNode {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<'a, V: Visitor + 'a> VisitorObject<'a> for V {
+    fn visit(&mut self, id: usize, label: &'static str) -> bool {
+        Visitor::visit(self, &ErasedNode { id, label })
+    }
+
+    fn clear(&mut self) {
+        Visitor::clear(self)
+    }
+
+    fn touch(&mut self, id: usize, label: &'static str, operation: Option<&'static str>) {
+        Visitor::touch(self, &ErasedNode { id, label }, operation)
+    }
+
+    fn notify_recalculated(&mut self, id: usize, label: &'static str) {
+        Visitor::notify_recalculated(self, &ErasedNode { id, label })
+    }
+
+    fn touch_dependency_group(&mut self, dep: &'static str) {
+        Visitor::touch_dependency_group(self, dep)
+    }
+
+    fn leave(&mut self, id: usize, label: &'static str) {
+        Visitor::leave(self, &ErasedNode { id, label })
+    }
+
+    fn hasher(&self) -> Box<dyn Hasher + 'a> {
+        Box::new(Visitor::hasher(self))
+    }
+
+    fn fork(&self) -> Option<ParallelVisitor> {
+        Visitor::fork(self)
+    }
+
+    fn take_cycle(&mut self) -> Option<Vec<NodeFrame>> {
+        Visitor::take_cycle(self)
+    }
+
+    fn skip_fresh(&self) -> bool {
+        Visitor::skip_fresh(self)
+    }
+}
+
+/// A [Visitor] of any type, allowing it to be passed through a trait object.
+pub(crate) struct DynVisitor<'a>(&'a mut dyn VisitorObject<'a>);
+
+impl<'a> DynVisitor<'a> {
+    pub fn new<V: Visitor>(visitor: &'a mut V) -> Self {
+        Self(visitor)
+    }
+}
+
+impl<'a> Visitor for DynVisitor<'a> {
+    type Hasher = Box<dyn Hasher + 'a>;
+
+    fn visit<N>(&mut self, node: &N) -> bool
+    where
+        N: Identifiable,
+    {
+        self.0.visit(node.id(), node.label())
+    }
+
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    fn touch<N>(&mut self, node: &N, operation: Option<&'static str>)
+    where
+        N: Identifiable,
+    {
+        self.0.touch(node.id(), node.label(), operation)
+    }
+
+    fn notify_recalculated<N>(&mut self, node: &N)
+    where
+        N: Identifiable,
+    {
+        self.0.notify_recalculated(node.id(), node.label())
+    }
+
+    fn touch_dependency_group(&mut self, dep: &'static str) {
+        self.0.touch_dependency_group(dep)
+    }
+
+    fn leave<N>(&mut self, node: &N)
+    where
+        N: Identifiable,
+    {
+        self.0.leave(node.id(), node.label())
+    }
+
+    fn hasher(&self) -> Self::Hasher {
+        self.0.hasher()
+    }
+
+    fn fork(&self) -> Option<ParallelVisitor> {
+        self.0.fork()
+    }
+
+    fn take_cycle(&mut self) -> Option<Vec<NodeFrame>> {
+        self.0.take_cycle()
+    }
+
+    fn skip_fresh(&self) -> bool {
+        self.0.skip_fresh()
+    }
+}
@@ -28,21 +28,16 @@ impl Visitor for HashBrownVisitor {
 
 #[cfg(all(test, not(miri)))]
 mod tests {
-    use serial_test::serial;
-
     use super::*;
     use crate::{
-        execution::{
-            identifiable::reset_node_id, internal_test_utils::TestData,
-            visitor::hash_one_ext::hash_one,
-        },
+        execution::{internal_test_utils::TestData, visitor::hash_one_ext::hash_one, NodeIds},
         InputNode,
     };
 
     #[test]
-    #[serial]
     fn test_hashbrown_visitor() {
-        reset_node_id();
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
         let node_1 = InputNode::new(TestData::new(5));
         let _ = InputNode::new(TestData::new(0));
         let node_2 = InputNode::new(TestData::new(6));
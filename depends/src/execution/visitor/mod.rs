@@ -1,18 +1,39 @@
+mod bit_set;
+mod epoch;
+mod erased;
 #[cfg(test)]
 mod hash_one_ext;
 #[cfg(feature = "hashbrown")]
 pub mod hashbrown;
+mod parallel;
+mod pool;
 
 use std::{
     collections::{hash_map::DefaultHasher, HashSet},
     hash::{BuildHasher, Hasher},
 };
 
-use super::Identifiable;
+pub use bit_set::BitSetVisitor;
+pub use epoch::EpochVisitor;
+pub(crate) use erased::DynVisitor;
+pub use parallel::ParallelVisitor;
+pub(crate) use parallel::{resolve_branches, Branch};
+
+use super::{error::NodeFrame, Identifiable};
 
 /// The default [Visitor] type.
+///
+/// > Nodes are tracked by Id alone. Ids are only unique within a
+/// > [NodeIds](crate::NodeIds) scope, so nodes of two graphs created in
+/// > different scopes collide, and one would be skipped as already visited.
+/// > Use a separate visitor for each graph.
 pub type HashSetVisitor = HashSet<usize>;
 
+/// Visitors indexed by node Id allocate memory up to the largest Id visited,
+/// so Ids from this one up are tracked in a [HashSet] instead. These are
+/// usually nodes created outside of any [NodeIds](crate::NodeIds) scope.
+const DENSE_IDS: usize = 1 << 24;
+
 /// A collection passed in to a graph, tracking the identifiers of each nodes to
 /// avoid traversing
 pub trait Visitor {
@@ -52,6 +73,33 @@ pub trait Visitor {
     }
 
     fn hasher(&self) -> Self::Hasher;
+
+    /// Create a branch of this visitor which can be sent to another thread,
+    /// sharing the same set of visited nodes. Thread-safe dependency groups
+    /// will resolve their dependencies in parallel if this returns a value.
+    fn fork(&self) -> Option<ParallelVisitor> {
+        None
+    }
+
+    /// If the last node which failed to [visit](Self::visit) is still being
+    /// resolved, such that reading it would never complete, the graph has a
+    /// cycle. Take the remainder of that cycle which isn't on this visitor's
+    /// stack: the nodes between the one which was reached again and the node
+    /// on this stack which it's waiting for, inclusive.
+    ///
+    /// Visitors which don't track the nodes being resolved return `None`, in
+    /// which case nodes detect cycles themselves.
+    fn take_cycle(&mut self) -> Option<Vec<NodeFrame>> {
+        None
+    }
+
+    /// Whether nodes which haven't been marked [stale](crate::Staleness)
+    /// since they were last resolved can skip resolving their dependencies.
+    /// Visitors which must see every node, such as those building graph
+    /// visualisations, should return `false`.
+    fn skip_fresh(&self) -> bool {
+        true
+    }
 }
 
 impl Visitor for HashSetVisitor {
@@ -0,0 +1,596 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{BuildHasher, RandomState},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+};
+
+use super::{pool::Pool, Visitor};
+use crate::execution::{
+    error::{NodeFrame, ResolveResult},
+    Identifiable,
+};
+
+/// The resolution state of a node claimed by a [ParallelVisitor].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ClaimState {
+    /// A branch is currently resolving this node.
+    Resolving,
+    /// This node has been resolved.
+    Resolved,
+}
+
+/// A branch which is waiting for a node claimed by another branch.
+#[derive(Debug)]
+struct Waiting {
+    /// The key of the waiting branch.
+    branch: usize,
+    /// The nodes being resolved by the waiting branch, ending with the node
+    /// it's waiting for.
+    path: Vec<NodeFrame>,
+}
+
+/// The nodes claimed by each branch of a [ParallelVisitor].
+#[derive(Debug, Default)]
+struct Claims {
+    /// Every node claimed since the last call to `clear`.
+    nodes: HashMap<usize, ClaimState>,
+    /// Every branch currently waiting for a node to be resolved.
+    waiting: Vec<Waiting>,
+}
+
+impl Claims {
+    /// Whether waiting for node `id` would never complete, as the branches
+    /// resolving it are (transitively) waiting for a node on `path`, which
+    /// is resolved by the waiting branch. If so, returns the nodes from `id`
+    /// to that node.
+    fn find_cycle(&self, path: &[NodeFrame], id: usize) -> Option<Vec<NodeFrame>> {
+        // The node being waited for isn't held by the waiting branch.
+        let held = match path.split_last() {
+            Some((last, held)) if last.id == id => held,
+            _ => path,
+        };
+        let mut rest = Vec::new();
+        self.find_cycle_from(held, id, &mut rest, &mut HashSet::new())
+            .then_some(rest)
+    }
+
+    fn find_cycle_from(
+        &self,
+        held: &[NodeFrame],
+        id: usize,
+        rest: &mut Vec<NodeFrame>,
+        searched: &mut HashSet<usize>,
+    ) -> bool {
+        if !searched.insert(id) {
+            return false;
+        }
+        for waiting in &self.waiting {
+            // A branch whose path passes through this node must finish before
+            // it's released. Follow it to the node it's waiting for.
+            let Some(position) = waiting.path.iter().position(|f| f.id == id) else {
+                continue;
+            };
+            let hop = &waiting.path[position + 1..];
+            let Some(next) = hop.last() else {
+                continue;
+            };
+            let len = rest.len();
+            rest.extend_from_slice(hop);
+            if held.iter().any(|f| f.id == next.id)
+                || self.find_cycle_from(held, next.id, rest, searched)
+            {
+                return true;
+            }
+            rest.truncate(len);
+        }
+        false
+    }
+}
+
+/// State shared between every branch of a [ParallelVisitor].
+#[derive(Debug)]
+struct SharedState {
+    /// The nodes claimed by, and waited on by, each branch.
+    claims: Mutex<Claims>,
+    /// Used to give each branch a unique key.
+    branches: AtomicUsize,
+    /// Signalled whenever a node moves to [ClaimState::Resolved].
+    resolved: Condvar,
+    /// Every node recalculated since the last call to `clear`.
+    recalculated: Mutex<HashSet<usize>>,
+    /// Shared so that every branch produces the same node hashes.
+    hash_builder: RandomState,
+    /// Runs the branches of each dependency group.
+    pool: Pool,
+}
+
+impl SharedState {
+    fn claims(&self) -> MutexGuard<'_, Claims> {
+        self.claims.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn release(&self, id: usize) {
+        self.claims().nodes.insert(id, ClaimState::Resolved);
+        self.resolved.notify_all();
+    }
+}
+
+/// A node touched by a [ParallelVisitor].
+#[derive(Debug, Copy, Clone)]
+struct Frame {
+    node: NodeFrame,
+    /// Whether this branch claimed the node when visiting it.
+    claimed: bool,
+}
+
+/// A [Visitor] which resolves the dependencies of thread-safe dependency
+/// groups (`SyncDependencies2..16`) at the same time, on scoped worker
+/// threads.
+///
+/// Each branch shares the set of visited nodes. If a branch visits a node
+/// which is currently being resolved by another branch, it will wait for that
+/// branch to finish before reading the node. This ensures that every node is
+/// resolved at most once, so caching semantics are identical to resolving the
+/// graph depth-first.
+///
+/// If a branch reaches a node which is being resolved further up its own
+/// path, or by a branch which is (transitively) waiting on it, the graph has
+/// a cycle. Rather than waiting forever, the node returns a
+/// [Cycle](crate::error::ResolveError::Cycle) naming every node in it.
+///
+/// The number of worker threads running at once is fixed when the visitor is
+/// created, and shared by each branch. A branch is only handed to a new
+/// thread while fewer than that are running; otherwise, it's resolved on the
+/// thread which reached it.
+/// Handing work between threads isn't free, so this is best suited to graphs
+/// with expensive, independent branches. Graphs of non thread-safe nodes are
+/// resolved sequentially.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use depends::{ParallelVisitor, SyncDependencies2, SyncDepRef2, SyncDerivedNode, SyncInputNode, Resolve, UpdateDerived};
+/// # use depends::derives::Operation;
+/// # use depends::error::EarlyExit;
+/// # #[derive(Operation)]
+/// # struct Add;
+/// # impl UpdateDerived<SyncDepRef2<'_, i64, i64>, Add> for i64 {
+/// #    fn update(
+/// #        &mut self,
+/// #        deps: SyncDepRef2<'_, i64, i64>,
+/// #        _: &Add,
+/// #    ) -> Result<(), EarlyExit> {
+/// #         *self = deps.0.data().value() + deps.1.data().value();
+/// #         Ok(())
+/// #    }
+/// # }
+/// let a = SyncInputNode::new(1_i64);
+/// let b = SyncInputNode::new(2_i64);
+/// let c = SyncInputNode::new(3_i64);
+/// let a_plus_b = SyncDerivedNode::new(
+///     SyncDependencies2::new(Arc::clone(&a), Arc::clone(&b)),
+///     Add,
+///     0_i64,
+/// );
+/// let b_plus_c = SyncDerivedNode::new(
+///     SyncDependencies2::new(Arc::clone(&b), Arc::clone(&c)),
+///     Add,
+///     0_i64,
+/// );
+/// // `a_plus_b` and `b_plus_c` will be resolved at the same time.
+/// let sum = SyncDerivedNode::new(
+///     SyncDependencies2::new(a_plus_b, b_plus_c),
+///     Add,
+///     0_i64,
+/// );
+///
+/// let mut visitor = ParallelVisitor::new();
+/// assert_eq!(*sum.resolve_root(&mut visitor).unwrap().value(), 8);
+/// ```
+#[derive(Debug)]
+pub struct ParallelVisitor {
+    shared: Arc<SharedState>,
+    /// Unique to this branch.
+    key: usize,
+    /// Nodes being resolved by the branches this branch was forked from.
+    ancestors: Vec<NodeFrame>,
+    /// Nodes being resolved by this branch.
+    stack: Vec<Frame>,
+    /// Set if the last node which failed to visit would never be resolved.
+    cycle: Option<Vec<NodeFrame>>,
+}
+
+impl Default for ParallelVisitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParallelVisitor {
+    /// Create a visitor with one worker thread fewer than the available
+    /// parallelism, as the resolving thread also resolves branches.
+    pub fn new() -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_threads(threads.saturating_sub(1).max(1))
+    }
+
+    /// Create a visitor with `threads` worker threads. With none, every
+    /// branch is resolved on the resolving thread.
+    pub fn with_threads(threads: usize) -> Self {
+        Self {
+            shared: Arc::new(SharedState {
+                claims: Mutex::default(),
+                branches: AtomicUsize::new(1),
+                resolved: Condvar::new(),
+                recalculated: Mutex::default(),
+                hash_builder: RandomState::new(),
+                pool: Pool::new(threads),
+            }),
+            key: 0,
+            ancestors: Vec::new(),
+            stack: Vec::new(),
+            cycle: None,
+        }
+    }
+
+    /// The identifiers of each node recalculated, across all branches, since
+    /// this visitor was last cleared.
+    pub fn recalculated(&self) -> HashSet<usize> {
+        self.shared
+            .recalculated
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Create a new branch of this visitor, sharing its visited nodes.
+    fn branch(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+            key: self.shared.branches.fetch_add(1, Ordering::Relaxed),
+            ancestors: self.path(),
+            stack: Vec::new(),
+            cycle: None,
+        }
+    }
+
+    /// The nodes being resolved by this branch, and those it was forked
+    /// from.
+    fn path(&self) -> Vec<NodeFrame> {
+        self.ancestors
+            .iter()
+            .copied()
+            .chain(self.stack.iter().map(|f| f.node))
+            .collect()
+    }
+
+    /// Whether a node is being resolved further up this branch, in which case
+    /// waiting for it would never complete.
+    fn is_ancestor(&self, id: usize) -> bool {
+        self.ancestors.iter().any(|f| f.id == id)
+            || self.stack.iter().any(|f| f.claimed && f.node.id == id)
+    }
+
+    /// Release every node this branch claimed but didn't leave. This happens
+    /// when a resolve returns early with an error.
+    fn release_all(&mut self) {
+        self.stack
+            .drain(..)
+            .filter(|f| f.claimed)
+            .for_each(|f| self.shared.release(f.node.id));
+    }
+}
+
+impl Drop for ParallelVisitor {
+    fn drop(&mut self) {
+        self.release_all();
+    }
+}
+
+impl Visitor for ParallelVisitor {
+    type Hasher = DefaultHasher;
+
+    fn visit<N>(&mut self, node: &N) -> bool
+    where
+        N: Identifiable,
+    {
+        self.cycle = None;
+        let id = node.id();
+        let mut claims = self.shared.claims();
+        loop {
+            match claims.nodes.get(&id).copied() {
+                None => {
+                    claims.nodes.insert(id, ClaimState::Resolving);
+                    if let Some(frame) = self.stack.last_mut().filter(|f| f.node.id == id) {
+                        frame.claimed = true;
+                    }
+                    return true;
+                }
+                Some(ClaimState::Resolved) => return false,
+                // The rest of the cycle is on this branch's path.
+                Some(ClaimState::Resolving) if self.is_ancestor(id) => {
+                    self.cycle = Some(Vec::new());
+                    return false;
+                }
+                Some(ClaimState::Resolving) => {
+                    let path = self.path();
+                    if let Some(rest) = claims.find_cycle(&path, id) {
+                        self.cycle = Some(rest);
+                        return false;
+                    }
+                    claims.waiting.push(Waiting {
+                        branch: self.key,
+                        path,
+                    });
+                    claims = self
+                        .shared
+                        .resolved
+                        .wait(claims)
+                        .unwrap_or_else(|e| e.into_inner());
+                    claims.waiting.retain(|w| w.branch != self.key);
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.release_all();
+        self.shared.claims().nodes.clear();
+        self.shared
+            .recalculated
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    fn touch<N>(&mut self, node: &N, operation: Option<&'static str>)
+    where
+        N: Identifiable,
+    {
+        self.stack.push(Frame {
+            node: NodeFrame::new(node, operation),
+            claimed: false,
+        });
+    }
+
+    fn notify_recalculated<N>(&mut self, node: &N)
+    where
+        N: Identifiable,
+    {
+        self.shared
+            .recalculated
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(node.id());
+    }
+
+    fn leave<N>(&mut self, node: &N)
+    where
+        N: Identifiable,
+    {
+        if let Some(frame) = self.stack.pop() {
+            debug_assert_eq!(frame.node.id, node.id());
+            if frame.claimed {
+                self.shared.release(frame.node.id);
+            }
+        }
+    }
+
+    fn hasher(&self) -> Self::Hasher {
+        self.shared.hash_builder.build_hasher()
+    }
+
+    fn fork(&self) -> Option<ParallelVisitor> {
+        Some(self.branch())
+    }
+
+    fn take_cycle(&mut self) -> Option<Vec<NodeFrame>> {
+        self.cycle.take()
+    }
+}
+
+/// A branch of a dependency group, resolved on its own thread.
+pub(crate) type Branch<'a> = &'a (dyn Fn(&mut ParallelVisitor) -> ResolveResult<()> + Sync);
+
+/// Resolve each branch at the same time on the visitor's threads, returning the
+/// first error encountered (if any) once every branch has completed.
+pub(crate) fn resolve_branches(
+    visitor: &ParallelVisitor,
+    branches: &[Branch<'_>],
+) -> ResolveResult<()> {
+    let jobs = branches
+        .iter()
+        .map(|branch| {
+            let mut fork = visitor.branch();
+            Box::new(move || branch(&mut fork)) as Box<dyn FnOnce() -> _ + Send + '_>
+        })
+        .collect();
+    visitor
+        .shared
+        .pool
+        .scope(jobs)
+        .into_iter()
+        .fold(Ok(()), Result::and)
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Condvar, Mutex,
+        },
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{
+        error::EarlyExit,
+        execution::{
+            internal_test_utils::TestData, Named, NodeIds, Resolve, SyncDependencies2,
+            SyncDependency, SyncDerivedNode, SyncInputNode, UpdateDerived,
+        },
+        SyncDepRef, SyncDepRef2,
+    };
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Waits for another node to be recalculated at the same time, returning
+    /// whether it was.
+    struct Meet(Arc<(Mutex<usize>, Condvar)>);
+
+    impl Named for Meet {
+        fn name() -> &'static str {
+            "Meet"
+        }
+    }
+
+    impl UpdateDerived<SyncDepRef<'_, TestData>, Meet> for TestData {
+        fn update(&mut self, deps: SyncDepRef<'_, TestData>, meet: &Meet) -> Result<(), EarlyExit> {
+            let (arrived, cvar) = &*meet.0;
+            let mut arrived = arrived.lock().unwrap();
+            *arrived += 1;
+            cvar.notify_all();
+            let (arrived, timeout) = cvar
+                .wait_timeout_while(arrived, Duration::from_secs(5), |arrived| *arrived < 2)
+                .unwrap();
+            assert!(!timeout.timed_out(), "branches weren't resolved at once");
+            drop(arrived);
+            self.inner = deps.data().inner;
+            Ok(())
+        }
+    }
+
+    impl UpdateDerived<SyncDepRef2<'_, TestData, TestData>, Meet> for TestData {
+        fn update(
+            &mut self,
+            deps: SyncDepRef2<'_, TestData, TestData>,
+            _: &Meet,
+        ) -> Result<(), EarlyExit> {
+            self.inner = deps.0.inner + deps.1.inner;
+            Ok(())
+        }
+    }
+
+    struct SlowAdd;
+
+    impl Named for SlowAdd {
+        fn name() -> &'static str {
+            "SlowAdd"
+        }
+    }
+
+    impl UpdateDerived<SyncDepRef<'_, TestData>, SlowAdd> for TestData {
+        fn update(&mut self, deps: SyncDepRef<'_, TestData>, _: &SlowAdd) -> Result<(), EarlyExit> {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            std::thread::sleep(Duration::from_millis(10));
+            self.inner = deps.data().inner + 1;
+            Ok(())
+        }
+    }
+
+    impl UpdateDerived<SyncDepRef2<'_, TestData, TestData>, SlowAdd> for TestData {
+        fn update(
+            &mut self,
+            deps: SyncDepRef2<'_, TestData, TestData>,
+            _: &SlowAdd,
+        ) -> Result<(), EarlyExit> {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            if deps.0.inner + deps.1.inner > 100 {
+                return Err(EarlyExit::new("too big"));
+            }
+            self.inner = deps.0.inner + deps.1.inner;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parallel_visitor() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        CALLS.store(0, Ordering::Relaxed);
+        // A diamond, where `shared` is visited by both branches.
+        let input = SyncInputNode::new(TestData::new(1));
+        let shared = SyncDerivedNode::new(
+            SyncDependency::new(Arc::clone(&input)),
+            SlowAdd,
+            TestData::new(0),
+        );
+        let left = SyncDerivedNode::new(
+            SyncDependency::new(Arc::clone(&shared)),
+            SlowAdd,
+            TestData::new(0),
+        );
+        let right = SyncDerivedNode::new(
+            SyncDependency::new(Arc::clone(&shared)),
+            SlowAdd,
+            TestData::new(0),
+        );
+        let root = SyncDerivedNode::new(
+            SyncDependencies2::new(Arc::clone(&left), Arc::clone(&right)),
+            SlowAdd,
+            TestData::new(0),
+        );
+
+        let mut visitor = ParallelVisitor::new();
+        {
+            let res = root.resolve(&mut visitor).unwrap();
+            assert_eq!(res.inner, 6);
+        }
+        // Each node is calculated exactly once.
+        assert_eq!(CALLS.load(Ordering::Relaxed), 4);
+        assert_eq!(visitor.recalculated(), HashSet::from([1, 2, 3, 4]));
+        visitor.clear();
+        assert_eq!(visitor.recalculated(), HashSet::new());
+
+        // Nothing has changed, so nothing is recalculated.
+        assert_eq!(root.resolve_root(&mut visitor).unwrap().inner, 6);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 4);
+
+        input.update(2).unwrap();
+        assert_eq!(root.resolve(&mut visitor).unwrap().inner, 8);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 8);
+        visitor.clear();
+
+        // An error in one branch doesn't prevent others from completing.
+        input.update(100).unwrap();
+        assert!(root.resolve_root(&mut visitor).is_err());
+        let calls = CALLS.load(Ordering::Relaxed);
+        assert_eq!(left.resolve_root(&mut visitor).unwrap().inner, 102);
+        assert_eq!(right.resolve_root(&mut visitor).unwrap().inner, 102);
+        assert_eq!(CALLS.load(Ordering::Relaxed), calls);
+        input.update(3).unwrap();
+        assert_eq!(root.resolve_root(&mut visitor).unwrap().inner, 10);
+    }
+
+    #[test]
+    fn test_parallel_visitor_overlaps() {
+        let ids = NodeIds::new();
+        let _scope = ids.enter();
+        let meet = Arc::new((Mutex::new(0), Condvar::new()));
+        let left = SyncDerivedNode::new(
+            SyncDependency::new(SyncInputNode::new(TestData::new(1))),
+            Meet(Arc::clone(&meet)),
+            TestData::new(0),
+        );
+        let right = SyncDerivedNode::new(
+            SyncDependency::new(SyncInputNode::new(TestData::new(2))),
+            Meet(Arc::clone(&meet)),
+            TestData::new(0),
+        );
+        let root = SyncDerivedNode::new(
+            SyncDependencies2::new(left, right),
+            Meet(Arc::clone(&meet)),
+            TestData::new(0),
+        );
+
+        // A single worker, alongside the resolving thread, is enough for
+        // both branches to run at once.
+        let mut visitor = ParallelVisitor::with_threads(1);
+        assert_eq!(root.resolve_root(&mut visitor).unwrap().inner, 3);
+        assert_eq!(*meet.0.lock().unwrap(), 2);
+    }
+}
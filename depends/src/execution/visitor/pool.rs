@@ -0,0 +1,152 @@
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// Runs jobs at the same time on scoped threads, up to a fixed number at
+/// once.
+///
+/// Each job handed to another thread gets a thread of its own, so a branch
+/// which blocks waiting on a node held by another branch can't prevent that
+/// branch from running. If the limit is reached, [scope](Self::scope) runs the
+/// remaining work on the calling thread.
+pub(crate) struct Pool {
+    threads: usize,
+    /// The number of threads currently running a job.
+    busy: AtomicUsize,
+}
+
+impl Pool {
+    /// Allow up to `threads` jobs to run on other threads at once. With none,
+    /// all work is run on the calling thread.
+    pub fn new(threads: usize) -> Self {
+        Self {
+            threads,
+            busy: AtomicUsize::new(0),
+        }
+    }
+
+    /// The number of threads which may run jobs at once.
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Run each job at the same time, using as many threads as are available
+    /// and running the rest on the calling thread in turn. Returns each
+    /// result in order, once every job has completed.
+    ///
+    /// A panic in any job is resumed on the calling thread, once every job
+    /// has completed.
+    pub fn scope<'a, T: Send + 'a>(&self, jobs: Vec<Box<dyn FnOnce() -> T + Send + 'a>>) -> Vec<T> {
+        thread::scope(|scope| {
+            let mut inline = Vec::new();
+            let mut spawned = Vec::new();
+            for (index, job) in jobs.into_iter().enumerate() {
+                // The calling thread would otherwise sit idle, so it always
+                // runs the first job.
+                let claim = (index > 0).then(|| self.claim()).flatten();
+                match claim {
+                    Some(claim) => {
+                        let handle = scope.spawn(move || {
+                            let _claim = claim;
+                            job()
+                        });
+                        spawned.push((index, handle));
+                    }
+                    None => inline.push((index, job)),
+                }
+            }
+            let mut results = inline
+                .into_iter()
+                .map(|(index, job)| (index, panic::catch_unwind(AssertUnwindSafe(job))))
+                .collect::<Vec<_>>();
+            results.extend(
+                spawned
+                    .into_iter()
+                    .map(|(index, handle)| (index, handle.join())),
+            );
+            results.sort_by_key(|(index, _)| *index);
+            results
+                .into_iter()
+                .map(|(_, result)| result.unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        })
+    }
+
+    /// Claim a thread to run a job on, if fewer than the limit are busy.
+    fn claim(&self) -> Option<Claim<'_>> {
+        self.busy
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |busy| {
+                (busy < self.threads).then_some(busy + 1)
+            })
+            .ok()
+            .map(|_| Claim(&self.busy))
+    }
+}
+
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("threads", &self.threads())
+            .finish()
+    }
+}
+
+/// A thread claimed from a [Pool], which is released when dropped.
+struct Claim<'a>(&'a AtomicUsize);
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(all(test, not(miri)))]
+mod tests {
+    use std::sync::Barrier;
+
+    use super::*;
+
+    #[test]
+    fn test_pool() {
+        let pool = Pool::new(2);
+        assert_eq!(format!("{pool:?}"), "Pool { threads: 2 }");
+        // Every job runs at once, or none could pass the barrier.
+        let barrier = Barrier::new(3);
+        let values = [1, 2, 3];
+        let jobs = values
+            .iter()
+            .map(|value| {
+                let barrier = &barrier;
+                Box::new(move || {
+                    barrier.wait();
+                    value * 2
+                }) as Box<dyn FnOnce() -> i32 + Send + '_>
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(pool.scope(jobs), vec![2, 4, 6]);
+
+        // Threads are released, and jobs which don't fit run in turn.
+        let jobs = (0..5)
+            .map(|i| Box::new(move || i) as Box<dyn FnOnce() -> i32 + Send>)
+            .collect::<Vec<_>>();
+        assert_eq!(pool.scope(jobs), vec![0, 1, 2, 3, 4]);
+        assert_eq!(Pool::new(0).scope(vec![Box::new(|| 1)]), vec![1]);
+    }
+
+    #[test]
+    fn test_pool_panic() {
+        let pool = Pool::new(1);
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.scope(vec![
+                Box::new(|| 1),
+                Box::new(|| -> i32 { panic!("job failed") }),
+            ])
+        }));
+        assert!(res.is_err());
+        // The thread is released.
+        assert_eq!(pool.scope(vec![Box::new(|| 2), Box::new(|| 3)]), vec![2, 3]);
+    }
+}
@@ -88,7 +88,7 @@ impl Node {
 /// # pub struct Sum;
 /// #
 /// # impl<A: NumberLike, B: NumberLike> UpdateDerived<DepRef2<'_, A, B>, Sum> for NumberValueI32 {
-/// #     fn update(&mut self, value: DepRef2<'_, A, B>) -> Result<(), EarlyExit> {
+/// #     fn update(&mut self, value: DepRef2<'_, A, B>, _: &Sum) -> Result<(), EarlyExit> {
 /// #         self.value = value.0.data().number_value() + value.1.data().number_value();
 /// #         Ok(())
 /// #     }
@@ -97,7 +97,7 @@ impl Node {
 /// # impl<A: NumberLike, B: NumberLike, C: NumberLike> UpdateDerived<DepRef3<'_, A, B, C>, Sum>
 /// # for NumberValueI32
 /// # {
-/// #     fn update(&mut self, value: DepRef3<'_, A, B, C>) -> Result<(), EarlyExit> {
+/// #     fn update(&mut self, value: DepRef3<'_, A, B, C>, _: &Sum) -> Result<(), EarlyExit> {
 /// #         self.value = value.0.data().number_value() + value.1.data().number_value() + value.2.data().number_value();
 /// #         Ok(())
 /// #     }
@@ -106,7 +106,7 @@ impl Node {
 /// # pub struct Square;
 /// #
 /// # impl<A: NumberLike> UpdateDerived<DepRef<'_, A>, Square> for NumberValueI32 {
-/// #     fn update(&mut self, value: DepRef<'_, A>) -> Result<(), EarlyExit> {
+/// #     fn update(&mut self, value: DepRef<'_, A>, _: &Square) -> Result<(), EarlyExit> {
 /// #         self.value = value.data().number_value().pow(2);
 /// #         Ok(())
 /// #     }
@@ -115,13 +115,17 @@ impl Node {
 /// # pub struct Multiply;
 /// #
 /// # impl<A: NumberLike, B: NumberLike> UpdateDerived<DepRef2<'_, A, B>, Multiply> for NumberValueI32 {
-/// #     fn update(&mut self, value: DepRef2<'_, A, B>) -> Result<(), EarlyExit> {
+/// #     fn update(&mut self, value: DepRef2<'_, A, B>, _: &Multiply) -> Result<(), EarlyExit> {
 /// #         self.value = value.0.data().number_value() * value.1.data().number_value();
 /// #         Ok(())
 /// #     }
 /// # }
 /// use depends::graphviz::GraphvizVisitor;
-/// use depends::NodeState;
+/// use depends::{NodeIds, NodeState};
+///
+/// // Nodes created in a scope have dense Ids, starting at zero.
+/// let ids = NodeIds::new();
+/// let _scope = ids.enter();
 ///
 /// let a = InputNode::new(NumberValueI32::new(1));
 /// let b = InputNode::new(NumberValueI32::new(2));
@@ -277,7 +281,7 @@ impl Visitor for GraphvizVisitor {
         self.nodes.entry(node.id()).or_insert_with(|| {
             Node {
                 id: node.id(),
-                name: N::name(),
+                name: node.label(),
                 edges: Vec::default(),
                 operation,
                 dependency: None,
@@ -308,4 +312,9 @@ impl Visitor for GraphvizVisitor {
     fn hasher(&self) -> Self::Hasher {
         self.visitor.hasher().build_hasher()
     }
+
+    fn skip_fresh(&self) -> bool {
+        // Every node must be touched to be rendered.
+        false
+    }
 }
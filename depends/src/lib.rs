@@ -42,6 +42,7 @@
 //! #    fn update(
 //! #        &mut self,
 //! #        deps: DepRef2<'_, i64, i32>,
+//! #        _: &Multiply,
 //! #    ) -> Result<(), EarlyExit> {
 //! #        *self = deps.0.data().value() * (*deps.1.data().value()as i64);
 //! #        Ok(())
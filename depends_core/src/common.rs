@@ -3,6 +3,8 @@ use proc_macro2::Span;
 pub const HASH: &str = "hash";
 pub const CUSTOM_CLEAN: &str = "custom_clean";
 pub const UNHASHABLE: &str = "unhashable";
+pub const VERSIONED: &str = "versioned";
+pub const HASH_128: &str = "hash_128";
 
 pub fn unexpected_attribute(attr: &str, span: Span) -> syn::Error {
     syn::Error::new(span, format!("Unexpected attribute \"{attr:?}\""))
@@ -12,6 +14,10 @@ pub fn duplicate_attribute(span: Span) -> syn::Error {
     syn::Error::new(span, "Attribute specified more than once")
 }
 
+pub fn conflicting_attribute(span: Span) -> syn::Error {
+    syn::Error::new(span, "Attribute conflicts with another attribute")
+}
+
 #[cfg(feature = "graphviz")]
 pub fn snake_case(string: &str) -> String {
     let mut result = String::new();
@@ -70,6 +70,7 @@ fn derive_dependencies_inner(input: TokenStream) -> syn::Result<TokenStream> {
     let mut field_new_args = TokenStream::new();
     let mut field_resolves = TokenStream::new();
     let mut dirty_field_args = Vec::<TokenStream>::new();
+    let mut subscribe_field_args = Vec::<TokenStream>::new();
     let mut generics = Generics::default();
     let mut rc_types = Vec::<TokenStream>::new();
     let mut names = Vec::<Ident>::new();
@@ -105,6 +106,9 @@ fn derive_dependencies_inner(input: TokenStream) -> syn::Result<TokenStream> {
         dirty_field_args.push(quote! {
             self.#ident.is_dirty()
         });
+        subscribe_field_args.push(quote! {
+            self.#ident.subscribe_stale(dependee)
+        });
         where_clauses.push(quote! {
                 for<#lifetime> #gen_ident: ::depends::Resolve<Output<#lifetime> = ::std::cell::Ref<#lifetime, ::depends::NodeState<#ty>>> + #lifetime
             });
@@ -176,6 +180,10 @@ fn derive_dependencies_inner(input: TokenStream) -> syn::Result<TokenStream> {
                     #field_resolves
                 })
             }
+
+            fn subscribe_stale(&self, dependee: &::std::rc::Rc<::depends::Staleness>) -> bool {
+                #(#subscribe_field_args)&&*
+            }
         }
 
         impl ::depends::IsDirty for #ref_ident <'_> {
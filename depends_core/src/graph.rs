@@ -90,11 +90,24 @@ fn derive_graph_inner(input: TokenStream) -> syn::Result<TokenStream> {
     let constraint = quote! {
         for<'a> R: ::depends::Resolve<Output<'a> = ::std::cell::Ref<'a, ::depends::NodeState<#root_type>>> + 'a,
     };
+    // Every value and operation owned by the graph, which are dropped on
+    // whichever thread the graph is sent to.
+    let mut owned_types: Vec<&Ident> = vec![];
+    for ty in input_types
+        .iter()
+        .chain(derived_types.iter())
+        .chain(derived.iter().map(|d| &d.operation))
+    {
+        if !owned_types.contains(&ty) {
+            owned_types.push(ty);
+        }
+    }
     // Safety: Since we only ever create these `Rc`s in this private type,
     // and no-one else can access them to clone them, their reference-count
-    // is constant and valid.
-    // TODO: I'm not 100% sure about behaviour on drop.
-    // TODO: you probably have to enforce Send as the bound on InputNode +
+    // is constant and valid. They're all moved together, so the graph is
+    // `Send` as long as everything they own is. The bounds are
+    // higher-ranked so that they're checked where the graph is sent, rather
+    // than here.
     Ok(quote! {
         mod #module_name {
             use super::*;
@@ -104,7 +117,10 @@ fn derive_graph_inner(input: TokenStream) -> syn::Result<TokenStream> {
                 #root_var_name: R
             }
 
-            unsafe impl<R> Send for #graph_ident<R> {}
+            unsafe impl<R> Send for #graph_ident<R>
+            where
+                #(for<'a> #owned_types: Send,)*
+            {}
 
             impl<R> #graph_ident<R>
             where
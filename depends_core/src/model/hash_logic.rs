@@ -10,12 +10,16 @@ pub enum HashLogic {
     Struct,
     /// Field is used as a has value.
     Field(Ident),
+    /// Node is versioned by the framework, rather than hashed.
+    Versioned,
 }
 
 impl HashLogic {
-    pub fn to_tokens(&self) -> TokenStream {
-        match self {
-            HashLogic::Struct => {
+    /// The body of `hash_value`. If `hash_128` is set, the value is hashed
+    /// to 128 bits.
+    pub fn to_tokens(&self, hash_128: bool) -> TokenStream {
+        match (self, hash_128) {
+            (HashLogic::Struct, false) => {
                 quote! {
                     ::depends::NodeHash::Hashed({
                         self.hash(hasher);
@@ -23,7 +27,12 @@ impl HashLogic {
                     })
                 }
             }
-            HashLogic::Field(ident) => {
+            (HashLogic::Struct, true) => {
+                quote! {
+                    ::depends::NodeHash::hashed_128(self, hasher)
+                }
+            }
+            (HashLogic::Field(ident), false) => {
                 quote! {
                     ::depends::NodeHash::Hashed({
                         self.#ident.hash(hasher);
@@ -31,11 +40,28 @@ impl HashLogic {
                     })
                 }
             }
-            HashLogic::Unhashable => {
+            (HashLogic::Field(ident), true) => {
+                quote! {
+                    ::depends::NodeHash::hashed_128(&self.#ident, hasher)
+                }
+            }
+            (HashLogic::Unhashable | HashLogic::Versioned, _) => {
                 quote! {
                     ::depends::NodeHash::NotHashed
                 }
             }
         }
     }
+
+    /// Any associated items of the `HashValue` implementation.
+    pub fn to_associated_tokens(&self) -> TokenStream {
+        match self {
+            HashLogic::Versioned => {
+                quote! {
+                    const VERSIONED: bool = true;
+                }
+            }
+            _ => TokenStream::new(),
+        }
+    }
 }
@@ -23,7 +23,7 @@ fn derive_value_inner(input: TokenStream) -> syn::Result<TokenStream> {
     } = parse2::<ItemStruct>(input)?;
 
     let name = ident.to_string();
-    let (custom_clean, hashing) = {
+    let (custom_clean, hashing, hash_128) = {
         let struct_attrs = get_depends_attrs(&attrs)?;
         let field_attrs: syn::Result<Vec<_>> = fields
             .iter()
@@ -52,6 +52,7 @@ fn derive_value_inner(input: TokenStream) -> syn::Result<TokenStream> {
         (
             parsed.custom_clean.unwrap_or(false),
             parsed.hashing.unwrap_or(HashLogic::Struct),
+            parsed.hash_128.unwrap_or(false),
         )
     };
 
@@ -67,7 +68,8 @@ fn derive_value_inner(input: TokenStream) -> syn::Result<TokenStream> {
         }
     };
 
-    let hash_value_clause = hashing.to_tokens();
+    let hash_value_clause = hashing.to_tokens(hash_128);
+    let associated_clause = hashing.to_associated_tokens();
 
     Ok(quote! {
         impl #impl_generics ::depends::Named for #ident #ty_generics #where_clause {
@@ -77,12 +79,16 @@ fn derive_value_inner(input: TokenStream) -> syn::Result<TokenStream> {
         }
 
         impl #impl_generics ::depends::HashValue for #ident #ty_generics #where_clause {
+            #associated_clause
+
             fn hash_value(&self, hasher: &mut impl ::std::hash::Hasher) -> ::depends::NodeHash {
                 use ::std::hash::Hash;
                 #hash_value_clause
             }
         }
 
+        impl #impl_generics ::depends::UpdateFn for #ident #ty_generics #where_clause {}
+
         #clean_clause
     })
 }
@@ -139,6 +145,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_input_versioned() {
+        let input = parse_quote! {
+            #[depends(versioned)]
+            struct Foo<T> {
+                bar: Vec<usize>,
+                number: usize,
+            }
+        };
+        assert_snapshot!(
+            "value_versioned",
+            format_source(derive_value(input).to_string().as_str())
+        );
+    }
+
+    #[test]
+    fn test_input_hash_128() {
+        let input = parse_quote! {
+            #[depends(hash_128)]
+            struct Foo<T> {
+                bar: Vec<usize>,
+                #[depends(hash)]
+                number: usize,
+            }
+        };
+        assert_snapshot!(
+            "value_hash_128",
+            format_source(derive_value(input).to_string().as_str())
+        );
+    }
+
+    #[test]
+    fn test_input_hash_128_versioned() {
+        let input = parse_quote! {
+            #[depends(hash_128, versioned)]
+            struct Foo {
+                bar: Vec<usize>,
+            }
+        };
+        assert_eq!(
+            derive_value_inner(input).unwrap_err().to_string(),
+            "Attribute conflicts with another attribute"
+        );
+    }
+
     #[test]
     fn test_input_generics_custom_clean() {
         let input = parse_quote! {
@@ -1,9 +1,13 @@
 use super::{field_attrs::ValueFieldAttr, struct_attrs::ValueStructAttr, ValueAttrModel};
-use crate::{common::duplicate_attribute, HashLogic};
+use crate::{
+    common::{conflicting_attribute, duplicate_attribute},
+    HashLogic,
+};
 
 pub struct ValueParsedAttrs {
     pub hashing: Option<HashLogic>,
     pub custom_clean: Option<bool>,
+    pub hash_128: Option<bool>,
 }
 
 impl TryFrom<ValueAttrModel> for ValueParsedAttrs {
@@ -13,7 +17,9 @@ impl TryFrom<ValueAttrModel> for ValueParsedAttrs {
         let mut this = Self {
             custom_clean: None,
             hashing: None,
+            hash_128: None,
         };
+        let mut hash_128_span = None;
         for v in attrs.struct_attrs.into_iter() {
             match v {
                 ValueStructAttr::Unhashable(s) => {
@@ -23,6 +29,21 @@ impl TryFrom<ValueAttrModel> for ValueParsedAttrs {
                         return Err(duplicate_attribute(s));
                     }
                 }
+                ValueStructAttr::Versioned(s) => {
+                    if this.hashing.is_none() {
+                        this.hashing = Some(HashLogic::Versioned);
+                    } else {
+                        return Err(duplicate_attribute(s));
+                    }
+                }
+                ValueStructAttr::Hash128(s) => {
+                    if this.hash_128.is_none() {
+                        this.hash_128 = Some(true);
+                        hash_128_span = Some(s);
+                    } else {
+                        return Err(duplicate_attribute(s));
+                    }
+                }
                 ValueStructAttr::CustomClean(s) => {
                     if this.custom_clean.is_none() {
                         this.custom_clean = Some(true);
@@ -42,6 +63,11 @@ impl TryFrom<ValueAttrModel> for ValueParsedAttrs {
                 }
             }
         }
+        if let (Some(s), Some(HashLogic::Unhashable | HashLogic::Versioned)) =
+            (hash_128_span, &this.hashing)
+        {
+            return Err(conflicting_attribute(s));
+        }
         Ok(this)
     }
 }
@@ -4,10 +4,12 @@ use syn::{
     Ident,
 };
 
-use crate::common::{unexpected_attribute, CUSTOM_CLEAN, UNHASHABLE};
+use crate::common::{unexpected_attribute, CUSTOM_CLEAN, HASH_128, UNHASHABLE, VERSIONED};
 
 pub enum ValueStructAttr {
     Unhashable(Span),
+    Versioned(Span),
+    Hash128(Span),
     CustomClean(Span),
 }
 
@@ -16,6 +18,8 @@ impl Parse for ValueStructAttr {
         let ident = input.parse::<Ident>()?;
         match ident.to_string().as_str() {
             UNHASHABLE => Ok(Self::Unhashable(ident.span())),
+            VERSIONED => Ok(Self::Versioned(ident.span())),
+            HASH_128 => Ok(Self::Hash128(ident.span())),
             CUSTOM_CLEAN => Ok(Self::CustomClean(ident.span())),
             unknown => Err(unexpected_attribute(unknown, ident.span())),
         }
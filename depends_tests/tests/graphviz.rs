@@ -2,12 +2,14 @@ use std::rc::Rc;
 
 use depends::{
     graphviz::GraphvizVisitor, Dependencies2, Dependencies3, Dependency, DerivedNode, InputNode,
-    Resolve,
+    NodeIds, Resolve, Select,
 };
 use examples::maths::*;
 
 #[test]
 fn test_graphviz() {
+    let ids = NodeIds::new();
+    let _scope = ids.enter();
     let a = InputNode::new(NumberValueI32::new(4));
     let b = InputNode::new(NumberValueI32::new(5));
     let c = InputNode::new(NumberValueI8::new(6));
@@ -59,3 +61,25 @@ digraph Dag {
         );
     }
 }
+
+#[test]
+fn test_graphviz_select() {
+    let ids = NodeIds::new();
+    let _scope = ids.enter();
+    let a = InputNode::new(NumberValueI32::new(4));
+    let value = Select::new(Rc::clone(&a), |a: &NumberValueI32| &a.value);
+
+    let mut visitor = GraphvizVisitor::new();
+    assert_eq!(*value.resolve(&mut visitor).unwrap(), 4);
+    assert_eq!(
+        r#"
+digraph Dag {
+  node_0 [label="NumberValueI32"];
+  node_1 [label="Select"];
+  node_0 -> node_1 [label="Select"];
+}
+    "#
+        .trim(),
+        visitor.render().unwrap()
+    );
+}
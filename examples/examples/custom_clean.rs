@@ -61,7 +61,11 @@ impl EfficientSequence {
 struct Totals;
 
 impl UpdateDerived<DepRef2<'_, Sequence, EfficientSequence>, Totals> for SequenceTotals {
-    fn update(&mut self, value: DepRef2<'_, Sequence, EfficientSequence>) -> Result<(), EarlyExit> {
+    fn update(
+        &mut self,
+        value: DepRef2<'_, Sequence, EfficientSequence>,
+        _: &Totals,
+    ) -> Result<(), EarlyExit> {
         // to calculate the total, we need to sum all the values in the
         // sequence every time this node is resolved.
         self.sequence_value = value.0.value.iter().sum();
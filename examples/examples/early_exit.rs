@@ -1,5 +1,7 @@
 use std::{
     collections::HashSet,
+    error::Error,
+    fmt,
     hash::{Hash, Hasher},
     rc::Rc,
 };
@@ -11,13 +13,12 @@ use depends::{
     UpdateInput,
 };
 
-/// A dummy tracker for the number of open orders.
+/// A dummy tracker for the number of open orders. Rather than hashing the
+/// orders, this node is given a new version every time it is updated.
 #[derive(Value, Default)]
+#[depends(versioned)]
 pub struct OpenOrders {
     pub value: Vec<i32>,
-    /// Keep track of every time this node is updated, use that as the hash.
-    #[depends(hash)]
-    generation: usize,
 }
 
 #[derive(Copy, Clone, Debug, Hash)]
@@ -38,7 +39,6 @@ impl UpdateInput for OpenOrders {
                 self.value.remove(0);
             }
         }
-        self.generation += 1;
     }
 }
 
@@ -55,14 +55,33 @@ impl RiskLimit {
     }
 }
 
+/// A structured error, which callers can branch on without inspecting
+/// strings.
+#[derive(Debug)]
+pub struct RiskLimitExceeded {
+    orders: usize,
+}
+
+impl fmt::Display for RiskLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Risk limit exceeded ({})", self.orders)
+    }
+}
+
+impl Error for RiskLimitExceeded {}
+
 #[derive(Operation)]
 struct CheckRiskLimit;
 
 impl UpdateDerived<DepRef<'_, OpenOrders>, CheckRiskLimit> for RiskLimit {
-    fn update(&mut self, value: DepRef<'_, OpenOrders>) -> Result<(), EarlyExit> {
+    fn update(
+        &mut self,
+        value: DepRef<'_, OpenOrders>,
+        _: &CheckRiskLimit,
+    ) -> Result<(), EarlyExit> {
         let orders = value.value.len();
         if orders >= self.max_orders {
-            Err(EarlyExit::new(format!("Risk limit exceeded ({orders})")))
+            Err(EarlyExit::custom(RiskLimitExceeded { orders }))
         } else {
             Ok(())
         }
@@ -80,7 +99,11 @@ pub struct ExpensiveCalculation {
 struct CalculateNextNumber;
 
 impl UpdateDerived<DepRef<'_, OpenOrders>, CalculateNextNumber> for ExpensiveCalculation {
-    fn update(&mut self, _: DepRef<'_, OpenOrders>) -> Result<(), EarlyExit> {
+    fn update(
+        &mut self,
+        _: DepRef<'_, OpenOrders>,
+        _: &CalculateNextNumber,
+    ) -> Result<(), EarlyExit> {
         println!("Expensive calculation performed!");
         self.next_number += 1;
         Ok(())
@@ -104,6 +127,7 @@ impl UpdateDerived<DepRef3<'_, OpenOrders, RiskLimit, ExpensiveCalculation>, Dec
     fn update(
         &mut self,
         value: DepRef3<'_, OpenOrders, RiskLimit, ExpensiveCalculation>,
+        _: &Decide,
     ) -> Result<(), EarlyExit> {
         self.value = Some(OpenOrdersOperation::Add(value.2.next_number));
         Ok(())
@@ -141,7 +165,12 @@ fn main() {
         let decision = match decision.resolve_root(&mut visitor) {
             Ok(order) => order.value.unwrap(),
             Err(ResolveError::EarlyExit(e)) => {
-                println!("Early exit: {e}, popping order");
+                match e.downcast_ref::<RiskLimitExceeded>() {
+                    Some(RiskLimitExceeded { orders }) => {
+                        println!("Early exit: {orders} orders open, popping order");
+                    }
+                    None => panic!("Unexpected early exit: {e}"),
+                }
                 OpenOrdersOperation::Cancel
             }
             _ => panic!("Unexpected error"),
@@ -8,7 +8,8 @@ use examples::maths::{NumberValueI32, NumberValueI8, *};
 // [Graph] macro.
 //
 // This has the added benefit of providing a safe `Send` implementation for
-// the graph, which is useful for threaded/async environments.
+// the graph (as long as each node value and operation is `Send`), which is
+// useful for threaded/async environments.
 //
 // Note that a graph is definitely _not_ `Sync`, seeing as it uses `RefCell`
 // for interior mutability.
@@ -45,7 +45,11 @@ impl CommentsToPosts {
 pub struct TrackCommentPostIds;
 
 impl UpdateDerived<DepRef<'_, Comments>, TrackCommentPostIds> for CommentsToPosts {
-    fn update(&mut self, value: DepRef<'_, Comments>) -> Result<(), EarlyExit> {
+    fn update(
+        &mut self,
+        value: DepRef<'_, Comments>,
+        _: &TrackCommentPostIds,
+    ) -> Result<(), EarlyExit> {
         for comment in value.new_comments() {
             let post_id = if let Some(post_id) = self.comments_to_posts.get(&comment.parent_id) {
                 *post_id
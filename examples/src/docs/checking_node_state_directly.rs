@@ -16,7 +16,11 @@ pub struct CheckBankBalance;
 
 // A dependency of time and money.
 impl UpdateDerived<DepRef2<'_, i64, i32>, CheckBankBalance> for StuffToBuy {
-    fn update(&mut self, deps: DepRef2<'_, i64, i32>) -> Result<(), EarlyExit> {
+    fn update(
+        &mut self,
+        deps: DepRef2<'_, i64, i32>,
+        _: &CheckBankBalance,
+    ) -> Result<(), EarlyExit> {
         // Is dirty is a trait implemented on all dependencies to indicate
         // that the inner value of this node has changed since last observed.
         if !deps.1.is_dirty() {
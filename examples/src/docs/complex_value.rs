@@ -1,8 +1,6 @@
 use std::collections::HashMap;
 
 use depends::{derives::Value, Clean, UpdateInput};
-use serial_test::serial;
-
 // ANCHOR: custom_clean
 struct Post {
     id: i64,
@@ -57,7 +55,6 @@ impl UpdateInput for Posts {
 }
 // ANCHOR_END: update_input
 
-#[serial]
 #[test]
 #[rustfmt::skip]
 fn create_input_node() {
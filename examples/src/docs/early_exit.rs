@@ -7,7 +7,7 @@ use crate::docs::simple_value::SomeNumber;
 pub struct CheckAllIsOk;
 
 impl UpdateDerived<DepRef<'_, SomeNumber>, CheckAllIsOk> for SomeNumber {
-    fn update(&mut self, deps: DepRef<'_, SomeNumber>) -> Result<(), EarlyExit> {
+    fn update(&mut self, deps: DepRef<'_, SomeNumber>, _: &CheckAllIsOk) -> Result<(), EarlyExit> {
         if deps.value >= 100 {
             return Err(EarlyExit::new("Things are a bit too spicy!"));
         }
@@ -16,3 +16,37 @@ impl UpdateDerived<DepRef<'_, SomeNumber>, CheckAllIsOk> for SomeNumber {
     }
 }
 // ANCHOR_END: early_exit
+
+#[test]
+#[rustfmt::skip]
+fn test_early_exit_trace() {
+use std::{collections::HashSet, rc::Rc};
+use depends::*;
+use crate::docs::simple_value::Square;
+let ids = NodeIds::new();
+let _scope = ids.enter();
+let input = InputNode::new(SomeNumber { value: 100 });
+let checked = DerivedNode::new(
+    Dependency::new(Rc::clone(&input)),
+    CheckAllIsOk,
+    SomeNumber::default(),
+);
+let squared = DerivedNode::new(
+    Dependency::new(Rc::clone(&checked)),
+    Square,
+    SomeNumber::default(),
+);
+// ANCHOR: early_exit_trace
+let err = squared.resolve_root(&mut HashSet::<usize>::new()).unwrap_err();
+
+// The error records the node which raised it...
+let node = err.node().unwrap();
+assert_eq!((node.id, node.name, node.operation), (1, "SomeNumber", Some("CheckAllIsOk")));
+
+// ...and the path to it from the node being resolved.
+assert_eq!(
+    err.to_string(),
+    "early exit: Things are a bit too spicy! at SomeNumber#2 (Square) -> SomeNumber#1 (CheckAllIsOk)"
+);
+// ANCHOR_END: early_exit_trace
+}
@@ -19,6 +19,24 @@ struct CustomHashStruct {
 }
 // ANCHOR_END: custom_hashing
 
+// ANCHOR: hash_128
+// This node is hashed to 128 bits, making collisions far less likely.
+#[derive(Value, Hash)]
+#[depends(hash_128)]
+struct WideHashStruct {
+    data: i32,
+}
+// ANCHOR_END: hash_128
+
+// ANCHOR: versioned
+// This node is given a new version each time it is updated.
+#[derive(Value)]
+#[depends(versioned)]
+struct VersionedStruct {
+    // ... your fields go here.
+}
+// ANCHOR_END: versioned
+
 // ANCHOR: no_hashing
 // This node will _always_ be considered dirty to its dependents.
 #[derive(Value)]
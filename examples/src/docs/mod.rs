@@ -5,6 +5,7 @@ mod early_exit;
 mod getting_started_value;
 mod hashing;
 mod multiple_dependencies;
+mod parameterised_operation;
 mod raising_the_stakes;
 mod reducing_more_boilerplate;
 mod simple_graph;
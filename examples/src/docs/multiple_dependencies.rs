@@ -8,7 +8,11 @@ use crate::docs::simple_value::SomeNumber;
 pub struct Multiply;
 
 impl UpdateDerived<DepRef2<'_, SomeNumber, SomeNumber>, Multiply> for SomeNumber {
-    fn update(&mut self, deps: DepRef2<'_, SomeNumber, SomeNumber>) -> Result<(), EarlyExit> {
+    fn update(
+        &mut self,
+        deps: DepRef2<'_, SomeNumber, SomeNumber>,
+        _: &Multiply,
+    ) -> Result<(), EarlyExit> {
         self.value = deps.0.value * deps.1.value;
         Ok(())
     }
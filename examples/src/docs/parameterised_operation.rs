@@ -0,0 +1,41 @@
+use depends::{derives::Operation, error::EarlyExit, DepRef, UpdateDerived};
+
+use crate::docs::simple_value::SomeNumber;
+
+// ANCHOR: clamp
+#[derive(Operation)]
+pub struct Clamp {
+    pub limit: i32,
+}
+
+impl UpdateDerived<DepRef<'_, SomeNumber>, Clamp> for SomeNumber {
+    fn update(&mut self, deps: DepRef<'_, SomeNumber>, operation: &Clamp) -> Result<(), EarlyExit> {
+        self.value = deps.value.min(operation.limit);
+        Ok(())
+    }
+}
+// ANCHOR_END: clamp
+
+#[test]
+#[rustfmt::skip]
+fn test_parameterised_operation() {
+use std::{collections::HashSet, rc::Rc};
+use depends::*;
+let input = InputNode::new(SomeNumber { value: 20 });
+// ANCHOR: create_clamp
+// The same operation can be reused with different parameters.
+let low = DerivedNode::new(
+    Dependency::new(Rc::clone(&input)),
+    Clamp { limit: 5 },
+    SomeNumber::default(),
+);
+let high = DerivedNode::new(
+    Dependency::new(Rc::clone(&input)),
+    Clamp { limit: 50 },
+    SomeNumber::default(),
+);
+// ANCHOR_END: create_clamp
+let mut visitor = HashSet::<usize>::new();
+assert_eq!(low.resolve_root(&mut visitor).unwrap().value, 5);
+assert_eq!(high.resolve_root(&mut visitor).unwrap().value, 20);
+}
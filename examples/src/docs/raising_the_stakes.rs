@@ -3,7 +3,6 @@ use depends::{
     error::EarlyExit,
     DepRef, DepRef2, UpdateDerived,
 };
-use serial_test::serial;
 
 use crate::docs::simple_value::SomeNumber;
 
@@ -11,7 +10,11 @@ use crate::docs::simple_value::SomeNumber;
 pub struct Add;
 
 impl UpdateDerived<DepRef2<'_, SomeNumber, SomeNumber>, Add> for SomeNumber {
-    fn update(&mut self, deps: DepRef2<'_, SomeNumber, SomeNumber>) -> Result<(), EarlyExit> {
+    fn update(
+        &mut self,
+        deps: DepRef2<'_, SomeNumber, SomeNumber>,
+        _: &Add,
+    ) -> Result<(), EarlyExit> {
         self.value = deps.0.value + deps.1.value;
         Ok(())
     }
@@ -21,7 +24,11 @@ impl UpdateDerived<DepRef2<'_, SomeNumber, SomeNumber>, Add> for SomeNumber {
 pub struct Subtract;
 
 impl UpdateDerived<DepRef2<'_, SomeNumber, SomeNumber>, Subtract> for SomeNumber {
-    fn update(&mut self, deps: DepRef2<'_, SomeNumber, SomeNumber>) -> Result<(), EarlyExit> {
+    fn update(
+        &mut self,
+        deps: DepRef2<'_, SomeNumber, SomeNumber>,
+        _: &Subtract,
+    ) -> Result<(), EarlyExit> {
         self.value = deps.0.value - deps.1.value;
         Ok(())
     }
@@ -36,26 +43,23 @@ pub struct AnotherNumber {
 pub struct Cube;
 
 impl UpdateDerived<DepRef<'_, SomeNumber>, Cube> for AnotherNumber {
-    fn update(&mut self, deps: DepRef<'_, SomeNumber>) -> Result<(), EarlyExit> {
+    fn update(&mut self, deps: DepRef<'_, SomeNumber>, _: &Cube) -> Result<(), EarlyExit> {
         self.value = deps.value.pow(3) as i64;
         Ok(())
     }
 }
 
-#[serial]
 #[test]
 #[rustfmt::skip]
 fn test_resolve_graph() {
 use super::multiple_dependencies::Multiply;
 use super::simple_value::Square;
-use depends::test_utils::{ext_reset_node_id};
 use depends::{
     graphviz::GraphvizVisitor, *
 };
 use std::rc::Rc;
-unsafe {
-    ext_reset_node_id();
-}
+let ids = NodeIds::new();
+let _scope = ids.enter();
 // ANCHOR: complex_graph
 // ANCHOR: boilerplate_setup
 let a = InputNode::new(SomeNumber { value: 1 });
@@ -1,5 +1,4 @@
 use depends::{derives::Graph, *};
-use serial_test::serial;
 
 use crate::docs::{multiple_dependencies::*, raising_the_stakes::*, simple_value::*};
 
@@ -40,7 +39,6 @@ use crate::docs::{multiple_dependencies::*, raising_the_stakes::*, simple_value:
 pub struct DagCreator;
 // ANCHOR_END: graph_creator
 
-#[serial]
 #[test]
 #[rustfmt::skip]
 fn test_reducing_more_boilerplate() {
@@ -1,19 +1,18 @@
-use serial_test::serial;
-
-#[serial]
 #[test]
 #[rustfmt::skip]
 fn test_resolve_graph() {
 use std::rc::Rc;
 use depends::{graphviz::GraphvizVisitor, *};
-use depends::test_utils::ext_reset_node_id;
 use crate::docs::{
     multiple_dependencies::Multiply,
     simple_value::SomeNumber,
 };
-unsafe {
-    ext_reset_node_id();
-}
+// ANCHOR: node_ids
+// Nodes created whilst this scope is entered are numbered from zero,
+// however many other graphs exist.
+let ids = NodeIds::new();
+let _scope = ids.enter();
+// ANCHOR_END: node_ids
 // ANCHOR: simple_graph
 let input_1 = InputNode::new(SomeNumber { value: 6 });
 let input_2 = InputNode::new(SomeNumber { value: 7 });
@@ -1,6 +1,4 @@
 use depends::{derives::Value, DepRef, UpdateDerived, UpdateInput};
-use serial_test::serial;
-
 #[rustfmt::skip]
 // Keep these separate to make the example clearer.
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -29,14 +27,13 @@ use depends::{derives::Operation, error::EarlyExit};
 pub struct Square;
 
 impl UpdateDerived<DepRef<'_, SomeNumber>, Square> for SomeNumber {
-    fn update(&mut self, deps: DepRef<'_, SomeNumber>) -> Result<(), EarlyExit> {
+    fn update(&mut self, deps: DepRef<'_, SomeNumber>, _: &Square) -> Result<(), EarlyExit> {
         self.value = deps.value.pow(2);
         Ok(())
     }
 }
 // ANCHOR_END: square
 
-#[serial]
 #[test]
 #[rustfmt::skip]
 fn test_update_input() {
@@ -55,7 +52,6 @@ assert_eq!(input.value().unwrap().value, 6);
 }
 
 #[test]
-#[serial]
 #[rustfmt::skip]
 fn test_update_derived() {
 use std::rc::Rc;
@@ -76,3 +72,27 @@ let derived = DerivedNode::new(
 let _ = input;
 let _ = derived;
 }
+
+#[test]
+#[rustfmt::skip]
+fn test_from_fn() {
+use std::{collections::HashSet, rc::Rc};
+use depends::{DerivedNode, InputNode, Dependency, Resolve};
+
+let input = InputNode::new(SomeNumber { value: 2 });
+// ANCHOR: from_fn
+let derived = DerivedNode::from_fn(
+    Dependency::new(Rc::clone(&input)),
+    // The operation is only labelled, without an `UpdateDerived` impl.
+    "Cube",
+    SomeNumber { value: 0 },
+    |target, deps| {
+        target.value = deps.value.pow(3);
+        Ok(())
+    },
+);
+// ANCHOR_END: from_fn
+
+let mut visitor = HashSet::<usize>::new();
+assert_eq!(derived.resolve_root(&mut visitor).unwrap().value, 8);
+}
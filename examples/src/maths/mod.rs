@@ -71,7 +71,7 @@ impl UpdateInput for NumberValueI8 {
 pub struct Sum;
 
 impl<A: NumberLike, B: NumberLike> UpdateDerived<DepRef2<'_, A, B>, Sum> for NumberValueI32 {
-    fn update(&mut self, value: DepRef2<'_, A, B>) -> Result<(), EarlyExit> {
+    fn update(&mut self, value: DepRef2<'_, A, B>, _: &Sum) -> Result<(), EarlyExit> {
         self.value = value.0.data().value() + value.1.data().value();
         Ok(())
     }
@@ -80,7 +80,7 @@ impl<A: NumberLike, B: NumberLike> UpdateDerived<DepRef2<'_, A, B>, Sum> for Num
 impl<A: NumberLike, B: NumberLike, C: NumberLike> UpdateDerived<DepRef3<'_, A, B, C>, Sum>
     for NumberValueI32
 {
-    fn update(&mut self, value: DepRef3<'_, A, B, C>) -> Result<(), EarlyExit> {
+    fn update(&mut self, value: DepRef3<'_, A, B, C>, _: &Sum) -> Result<(), EarlyExit> {
         self.value = value.0.data().value() + value.1.data().value() + value.2.data().value();
         Ok(())
     }
@@ -90,7 +90,7 @@ impl<A: NumberLike, B: NumberLike, C: NumberLike> UpdateDerived<DepRef3<'_, A, B
 pub struct Square;
 
 impl<A: NumberLike> UpdateDerived<DepRef<'_, A>, Square> for NumberValueI32 {
-    fn update(&mut self, value: DepRef<'_, A>) -> Result<(), EarlyExit> {
+    fn update(&mut self, value: DepRef<'_, A>, _: &Square) -> Result<(), EarlyExit> {
         self.value = value.data().value().pow(2);
         Ok(())
     }
@@ -99,7 +99,7 @@ impl<A: NumberLike> UpdateDerived<DepRef<'_, A>, Square> for NumberValueI32 {
 #[derive(Operation)]
 pub struct Multiply;
 impl<A: NumberLike, B: NumberLike> UpdateDerived<DepRef2<'_, A, B>, Multiply> for NumberValueI32 {
-    fn update(&mut self, value: DepRef2<'_, A, B>) -> Result<(), EarlyExit> {
+    fn update(&mut self, value: DepRef2<'_, A, B>, _: &Multiply) -> Result<(), EarlyExit> {
         self.value = value.0.data().value() * value.1.data().value();
         Ok(())
     }
@@ -18,19 +18,16 @@ struct PostScore {
     id: i64,
 }
 
+/// Versioned rather than hashed, as hashing every score would be costlier
+/// than recalculating any dependees.
 #[derive(Debug, Default, Value)]
+#[depends(versioned)]
 pub struct PostScoresQuery {
     /// Map of post id to scores.
     post_scores: HashMap<i64, PostScore>,
     /// Map of comments to posts.
     /// The top 3 posts.
     top_posts: BinaryHeap<Reverse<PostScore>>,
-    /// Use whether the top posts has changed as a hash. This is unnecessary
-    /// as no node depends on this one, but it's a good example of how to
-    /// cache multiple nodes behind a single node, which will dramatically
-    /// increase the performance of further dependees.
-    #[depends(hash)]
-    top_posts_generation: usize,
 }
 
 impl PostScoresQuery {
@@ -38,7 +35,6 @@ impl PostScoresQuery {
         Self {
             post_scores: HashMap::with_capacity(512),
             top_posts: BinaryHeap::with_capacity(3),
-            top_posts_generation: 0,
         }
     }
 
@@ -57,17 +53,17 @@ impl PostScoresQuery {
             .ok_or_else(|| EarlyExit::new(format!("No score found for post id {post_id}")))
     }
 
-    fn update_post_score(&mut self, post_id: i64, score: u32) -> Result<bool, EarlyExit> {
+    fn update_post_score(&mut self, post_id: i64, score: u32) -> Result<(), EarlyExit> {
         let post_score = self.get_post_id_mut(post_id)?;
         post_score.score += score;
         let post_score = post_score.clone();
-        Ok(self.update_top_posts(post_score))
+        self.update_top_posts(post_score);
+        Ok(())
     }
 
-    fn update_top_posts(&mut self, post_score: PostScore) -> bool {
+    fn update_top_posts(&mut self, post_score: PostScore) {
         if self.top_posts.len() < 3 {
             self.top_posts.push(Reverse(post_score));
-            return true;
         } else {
             let smallest = self.top_posts.peek().unwrap();
             if post_score > smallest.0 {
@@ -80,10 +76,8 @@ impl PostScoresQuery {
                     self.top_posts.pop();
                     self.top_posts.push(Reverse(post_score));
                 }
-                return true;
             }
         }
-        false
     }
 
     fn retain_top_posts(&mut self, other_than: i64) {
@@ -107,6 +101,7 @@ impl UpdateDerived<DepRef4<'_, Comments, CommentsToPosts, Posts, Likes>, UpdateP
     fn update(
         &mut self,
         value: DepRef4<'_, Comments, CommentsToPosts, Posts, Likes>,
+        _: &UpdatePostScoresQuery,
     ) -> Result<(), EarlyExit> {
         for post in value.2.new_posts() {
             self.post_scores.insert(
@@ -119,21 +114,15 @@ impl UpdateDerived<DepRef4<'_, Comments, CommentsToPosts, Posts, Likes>, UpdateP
             );
         }
 
-        let mut delta = 0;
         for comment in value.0.new_comments() {
             let post_id = value.1.get_post_id(comment.id)?;
-            if self.update_post_score(post_id, 10)? {
-                delta = 1;
-            }
+            self.update_post_score(post_id, 10)?;
         }
 
         for like in value.3.new_likes() {
             let post_id = value.1.get_post_id(like.comment_id)?;
-            if self.update_post_score(post_id, 1)? {
-                delta = 1;
-            }
+            self.update_post_score(post_id, 1)?;
         }
-        self.top_posts_generation += delta;
         Ok(())
     }
 }